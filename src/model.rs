@@ -1,3 +1,8 @@
 pub mod tokening;
 pub mod parsing;
-pub mod physics;
\ No newline at end of file
+pub mod physics;
+pub mod diagnostics;
+pub mod units;
+pub mod vectors;
+pub mod random;
+pub mod triggers;
\ No newline at end of file
@@ -0,0 +1,44 @@
+//! Conserved quantities of the world, used to judge the quality of the simulation.
+
+use raylib::prelude::*;
+use super::physics::World;
+
+/// Totals over every point of the world. Angular momentum is measured around the origin.
+#[derive(Clone, Copy, Debug)]
+pub struct Diagnostics {
+	pub kinetic_energy: f32,
+	pub potential_energy: f32,
+	/// Whether every force has a potential, `potential_energy` missing some terms otherwise.
+	pub potential_complete: bool,
+	pub momentum: Vector2,
+	pub angular_momentum: f32
+}
+
+impl Diagnostics {
+	pub fn measure(world: &World) -> Self {
+		let mut d = Diagnostics {
+			kinetic_energy: 0f32,
+			potential_energy: 0f32,
+			potential_complete: true,
+			momentum: Vector2::zero(),
+			angular_momentum: 0f32
+		};
+
+		for point in world.iter() {
+			let (position, speed, mass) = (point.position(), point.speed(), point.mass());
+			let (potential, complete) = point.potential_energy();
+
+			d.kinetic_energy += 0.5f32 * mass * speed.length_sqr();
+			d.potential_energy += potential;
+			d.potential_complete &= complete;
+			d.momentum += speed * mass;
+			d.angular_momentum += mass * (position.x * speed.y - position.y * speed.x);
+		}
+
+		d
+	}
+
+	pub fn total_energy(&self) -> f32 {
+		self.kinetic_energy + self.potential_energy
+	}
+}
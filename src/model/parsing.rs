@@ -1,6 +1,11 @@
 use super::tokening::*;
+use super::units::*;
+use super::vectors::TypeError;
+use super::random::{RandomStream, RANDOM_FUNCTIONS};
 
 use std::collections::HashMap;
+use std::f32::consts::{PI, E};
+use std::fmt;
 
 #[derive(Debug)]
 pub enum ParsingError {
@@ -10,25 +15,679 @@ pub enum ParsingError {
 	NoOperator,
 	NotAValue,
 	MissingVariableInContext (String),
-	EmptyTokenData
+	EmptyTokenData,
+	UnknownFunction (String),
+	WrongArgumentCount (String, usize),
+	MisplacedComma,
+	MisplacedAssignment,
+	/// A user function calls itself, directly or through other functions.
+	RecursiveFunction (String),
+	/// A user function can't be removed while another one calls it.
+	FunctionInUse (String, String), // Function, caller
+	/// A user function can't take the name of a built-in function.
+	BuiltinFunction (String),
+	/// Vectors and scalars used inconsistently.
+	Vector (TypeError)
 }
 
 pub type VariableContext = HashMap::<String, f32>;
 
+/// Named constants available in expressions, unless the context defines a variable of the same name.
+/// `g` is the standard gravity.
+pub const CONSTANTS: [(&str, f32); 3] = [("pi", PI), ("e", E), ("g", 9.81f32)];
+
 struct OperatorPosition (char, usize);
 
+/// Tree of the operations of an expression, built from its tokens by `Parser::build`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+	Value (f32),
+	/// Value written with a unit, e.g. `9.81[m/s^2]`
+	Quantity (f32, Dimension),
+	Variable (String),
+	Negation (Box::<Expression>),
+	/// Logical not, 1 if the operand is 0 and 0 otherwise
+	Not (Box::<Expression>),
+	Operation (char, Box::<Expression>, Box::<Expression>),
+	Call (String, Vec::<Expression>)
+}
+
+impl Expression {
+	pub fn evaluate(&self, context: &VariableContext) -> Result<f32, ParsingError> {
+		self.evaluate_with(context, &mut RandomStream::default())
+	}
+
+	/// Evaluates the expression, random functions drawing their numbers from `random`.
+	pub fn evaluate_with(&self, context: &VariableContext, random: &mut RandomStream) -> Result<f32, ParsingError> {
+		match self {
+			Expression::Value(v) | Expression::Quantity(v, _) => Ok(*v),
+			Expression::Variable(name) => match (context.get(name), CONSTANTS.iter().find(|(n, _)| n == name)) {
+				(Some(val), _) | (None, Some((_, val))) => Ok(*val),
+				(None, None) => Err(ParsingError::MissingVariableInContext(name.clone()))
+			},
+			Expression::Negation(e) => Ok(-e.evaluate_with(context, random)?),
+			Expression::Not(e) => Ok((e.evaluate_with(context, random)? == 0f32) as i32 as f32),
+			Expression::Operation(op, left, right) => {
+				let left_hand = left.evaluate_with(context, random)?;
+				// Logical operators don't evaluate their right operand when the left one decides
+				match (op, left_hand != 0f32) {
+					('&', false) => return Ok(0f32),
+					('|', true) => return Ok(1f32),
+					_ => {}
+				}
+
+				let right_hand = right.evaluate_with(context, random)?;
+				match op {
+					'+' => Ok(left_hand + right_hand),
+					'-' => Ok(left_hand - right_hand),
+					'/' => Ok(left_hand / right_hand),
+					'*' => Ok(left_hand * right_hand),
+					'^' => Ok(left_hand.powf(right_hand)),
+					'>' => Ok((left_hand > right_hand) as i32 as f32),
+					'<' => Ok((left_hand < right_hand) as i32 as f32),
+					'≥' => Ok((left_hand >= right_hand) as i32 as f32),
+					'≤' => Ok((left_hand <= right_hand) as i32 as f32),
+					'=' => Ok((left_hand == right_hand) as i32 as f32),
+					'≠' => Ok((left_hand != right_hand) as i32 as f32),
+					'&' | '|' => Ok((right_hand != 0f32) as i32 as f32),
+					 _  => Err(ParsingError::UnknownOperator(*op))
+				}
+			},
+			// Only the selected branch is evaluated
+			Expression::Call(name, args) if name == "if" => match args.as_slice() {
+				[condition, a, b] => if condition.evaluate_with(context, random)? != 0f32 {a.evaluate_with(context, random)} else {b.evaluate_with(context, random)},
+				_ => Err(ParsingError::WrongArgumentCount(name.clone(), args.len()))
+			},
+			// Sums over the world are measured beforehand, under the name of their call
+			Expression::Call(name, _) if name == "sum" => {
+				let key = self.to_string();
+				match context.get(&key) {
+					Some(v) => Ok(*v),
+					None => Err(ParsingError::MissingVariableInContext(key))
+				}
+			},
+			Expression::Call(name, args) => {
+				let a = args.iter().map(|e| e.evaluate_with(context, random)).collect::<Result<Vec::<f32>, ParsingError>>()?;
+				match (name.as_str(), a.as_slice()) {
+					("sin", [x]) => Ok(x.sin()),
+					("cos", [x]) => Ok(x.cos()),
+					("tan", [x]) => Ok(x.tan()),
+					("sqrt", [x]) => Ok(x.sqrt()),
+					("abs", [x]) => Ok(x.abs()),
+					("exp", [x]) => Ok(x.exp()),
+					("ln", [x]) => Ok(x.ln()),
+					("min", [x, y]) => Ok(x.min(*y)),
+					("max", [x, y]) => Ok(x.max(*y)),
+					("atan2", [y, x]) => Ok(y.atan2(*x)),
+					("rand", []) => Ok(random.uniform()),
+					("randn", []) => Ok(random.normal()),
+					("noise", [x, y, t]) => Ok(random.noise(*x, *y, *t)),
+					_ => Err(ParsingError::UnknownFunction(name.clone()))
+				}
+			}
+		}
+	}
+
+	/// Symbolic derivative with respect to `variable`. Comparisons and logical operators are
+	/// step functions whose derivative is taken as 0.
+	pub fn derivative(&self, variable: &str) -> Expression {
+		use Expression::*;
+
+		match self {
+			Value(_) | Quantity(..) => Value(0f32),
+			Variable(name) => Value(if name == variable {1f32} else {0f32}),
+			Negation(e) => Negation(Box::new(e.derivative(variable))),
+			Not(_) => Value(0f32),
+			Operation(op, left, right) => {
+				let (dl, dr) = (Box::new(left.derivative(variable)), Box::new(right.derivative(variable)));
+				match op {
+					'+' | '-' => Operation(*op, dl, dr),
+					// (uv)' = u'v + uv'
+					'*' => Operation('+',
+						Box::new(Operation('*', dl, right.clone())),
+						Box::new(Operation('*', left.clone(), dr))
+					),
+					// (u/v)' = (u'v - uv') / v²
+					'/' => Operation('/',
+						Box::new(Operation('-',
+							Box::new(Operation('*', dl, right.clone())),
+							Box::new(Operation('*', left.clone(), dr))
+						)),
+						Box::new(Operation('*', right.clone(), right.clone()))
+					),
+					'^' => match dr.simplify() {
+						// (u^n)' = n*u^(n-1)*u'
+						Value(0f32) => Operation('*',
+							Box::new(Operation('*',
+								right.clone(),
+								Box::new(Operation('^', left.clone(), Box::new(Operation('-', right.clone(), Box::new(Value(1f32))))))
+							)),
+							dl
+						),
+						// (u^v)' = u^v * (v'*ln(u) + v*u'/u)
+						dr => Operation('*',
+							Box::new(self.clone()),
+							Box::new(Operation('+',
+								Box::new(Operation('*', Box::new(dr), Box::new(Call("ln".to_string(), vec![*left.clone()])))),
+								Box::new(Operation('/', Box::new(Operation('*', right.clone(), dl)), left.clone()))
+							))
+						)
+					},
+					_ => Value(0f32)
+				}
+			},
+			Call(name, args) => {
+				let mul = |a: Expression, b: Expression| Operation('*', Box::new(a), Box::new(b));
+				let div = |a: Expression, b: Expression| Operation('/', Box::new(a), Box::new(b));
+				let call = |f: &str, a: &Expression| Call(f.to_string(), vec![a.clone()]);
+				let derivatives = args.iter().map(|a| a.derivative(variable)).collect::<Vec::<Expression>>();
+
+				match (name.as_str(), args.as_slice(), derivatives.as_slice()) {
+					("sin", [u], [du]) => mul(call("cos", u), du.clone()),
+					("cos", [u], [du]) => Negation(Box::new(mul(call("sin", u), du.clone()))),
+					("tan", [u], [du]) => div(du.clone(), mul(call("cos", u), call("cos", u))),
+					("sqrt", [u], [du]) => div(du.clone(), mul(Value(2f32), call("sqrt", u))),
+					// Sign of u times u'
+					("abs", [u], [du]) => mul(
+						Operation('-',
+							Box::new(Operation('>', Box::new(u.clone()), Box::new(Value(0f32)))),
+							Box::new(Operation('<', Box::new(u.clone()), Box::new(Value(0f32))))
+						),
+						du.clone()
+					),
+					("exp", [u], [du]) => mul(call("exp", u), du.clone()),
+					("ln", [u], [du]) => div(du.clone(), u.clone()),
+					// The derivative of the selected argument
+					("min", [a, b], [da, db]) | ("max", [a, b], [da, db]) => {
+						let first = Operation(if name == "min" {'<'} else {'>'}, Box::new(a.clone()), Box::new(b.clone()));
+						Operation('+',
+							Box::new(mul(first.clone(), da.clone())),
+							Box::new(mul(Operation('-', Box::new(Value(1f32)), Box::new(first)), db.clone()))
+						)
+					},
+					("if", [condition, ..], [_, da, db]) => Call(name.clone(), vec![condition.clone(), da.clone(), db.clone()]),
+					// (x*y' - y*x') / (x² + y²)
+					("atan2", [y, x], [dy, dx]) => div(
+						Operation('-', Box::new(mul(x.clone(), dy.clone())), Box::new(mul(y.clone(), dx.clone()))),
+						Operation('+', Box::new(mul(x.clone(), x.clone())), Box::new(mul(y.clone(), y.clone())))
+					),
+					_ => Value(0f32)
+				}
+			}
+		}
+	}
+
+	/// Equivalent expression with constant operations folded and neutral elements removed,
+	/// e.g. `x*1`, `x + 0` or `x - x`.
+	pub fn simplify(&self) -> Expression {
+		use Expression::*;
+
+		match self {
+			Value(_) | Quantity(..) | Variable(_) => self.clone(),
+			Negation(e) => match e.simplify() {
+				// Avoids writing -0
+				Value(v) => Value(if v == 0f32 {0f32} else {-v}),
+				Quantity(v, d) => Quantity(if v == 0f32 {0f32} else {-v}, d),
+				Negation(inner) => *inner,
+				e => Negation(Box::new(e))
+			},
+			Not(e) => match e.simplify() {
+				Value(v) => Value((v == 0f32) as i32 as f32),
+				e => Not(Box::new(e))
+			},
+			Operation(op, left, right) => match (*op, left.simplify(), right.simplify()) {
+				(op, Value(a), Value(b)) => {
+					let folded = Operation(op, Box::new(Value(a)), Box::new(Value(b)));
+					// Infinities and NaN are kept as operations since they can't be written as values
+					match folded.evaluate(&VariableContext::new()) {
+						Ok(v) if v.is_finite() => Value(v),
+						_ => folded
+					}
+				},
+				('+', Value(0f32), e) | ('+', e, Value(0f32)) => e,
+				('+', a, b) if a == b && !a.is_random() => Operation('*', Box::new(Value(2f32)), Box::new(a)).simplify(),
+				('+', a, Negation(b)) => Operation('-', Box::new(a), b),
+				('-', e, Value(0f32)) => e,
+				('-', Value(0f32), e) => Negation(Box::new(e)).simplify(),
+				('-', a, b) if a == b && !a.is_random() => Value(0f32),
+				('-', a, Negation(b)) => Operation('+', Box::new(a), b),
+				('*', Value(0f32), _) | ('*', _, Value(0f32)) => Value(0f32),
+				('*', Value(1f32), e) | ('*', e, Value(1f32)) => e,
+				('*', Value(-1f32), e) | ('*', e, Value(-1f32)) => Negation(Box::new(e)).simplify(),
+				('*', Value(a), Operation('*', l, r)) => match *l {
+					Value(b) => Operation('*', Box::new(Value(a * b)), r),
+					l => Operation('*', Box::new(Value(a)), Box::new(Operation('*', Box::new(l), r)))
+				},
+				('^', e, Value(1f32)) => e,
+				('^', _, Value(0f32)) => Value(1f32),
+				('/', e, Value(1f32)) => e,
+				('/', Value(0f32), _) => Value(0f32),
+				(op, l, r) => Operation(op, Box::new(l), Box::new(r))
+			},
+			Call(name, args) => {
+				let args = args.iter().map(|a| a.simplify()).collect::<Vec::<Expression>>();
+				// A constant condition selects its branch
+				if let ("if", [Value(condition), a, b]) = (name.as_str(), args.as_slice()) {
+					return if *condition != 0f32 {a.clone()} else {b.clone()};
+				}
+				let constant = args.iter().all(|a| matches!(a, Value(_))) && !RANDOM_FUNCTIONS.contains(&name.as_str());
+				let call = Call(name.clone(), args);
+				match call.evaluate(&VariableContext::new()) {
+					Ok(v) if constant && v.is_finite() => Value(v),
+					_ => call
+				}
+			}
+		}
+	}
+
+	/// Dimension of the expression given the units of its variables, `None` when it can be anything.
+	/// Numbers without unit are plain numbers, so that `2*px + vx` is refused like `px + vx`.
+	/// A number added to, compared with or selected instead of a quantity is read in its unit
+	/// though, `px - 400` being a length, and a plain number may be used as any quantity.
+	/// This is the dimensional analysis pass, which also checks that every sum, comparison and
+	/// function argument is consistent.
+	pub fn dimension(&self) -> Result<Option<Dimension>, DimensionError> {
+		self.dimension_in(&HashMap::new())
+	}
+
+	/// Dimension of the expression, the names in `bindings` having the given dimension
+	/// instead of the one of the variable they shadow.
+	pub fn dimension_in(&self, bindings: &HashMap::<String, Option<Dimension>>) -> Result<Option<Dimension>, DimensionError> {
+		// Dimension shared by two operands, a plain number taking the dimension of the other one
+		let same = |(a, da): (&Expression, Option<Dimension>), (b, db): (&Expression, Option<Dimension>)| match (da, db) {
+			_ if a.is_plain_number() => Ok(db),
+			_ if b.is_plain_number() => Ok(da),
+			(Some(da), Some(db)) if da != db => Err(DimensionError::Mismatch(self.to_string(), da, db)),
+			_ => Ok(da.or(db))
+		};
+
+		match self {
+			Expression::Value(_) => Ok(Some(Dimension::NONE)),
+			Expression::Quantity(_, d) => Ok(Some(*d)),
+			Expression::Variable(name) => Ok(bindings.get(name).copied().unwrap_or_else(|| variable_dimension(name))),
+			Expression::Negation(e) => e.dimension_in(bindings),
+			Expression::Not(e) => e.dimension_in(bindings).map(|_| Some(Dimension::NONE)),
+			Expression::Operation(op, left, right) => {
+				let (l, r) = (left.dimension_in(bindings)?, right.dimension_in(bindings)?);
+				match (op, l, r) {
+					('+' | '-', l, r) => same((left, l), (right, r)),
+					// Comparisons and logical operators are plain numbers, 0 or 1
+					('>' | '<' | '≥' | '≤' | '=' | '≠', l, r) => same((left, l), (right, r)).map(|_| Some(Dimension::NONE)),
+					('&' | '|', _, _) => Ok(Some(Dimension::NONE)),
+					('*', Some(l), Some(r)) => Ok(Some(l * r)),
+					('/', Some(l), Some(r)) => Ok(Some(l / r)),
+					// Exponents are plain numbers, and must be known integers for the base to have a unit
+					('^', _, Some(e)) if e != Dimension::NONE => Err(DimensionError::NotDimensionless(format!("the exponent of {self}"), e)),
+					('^', Some(d), _) if d != Dimension::NONE => match right.simplify() {
+						Expression::Value(n) if n.fract() == 0f32 => Ok(Some(d.pow(n as i8))),
+						_ => Err(DimensionError::NonIntegerPower(self.to_string(), d))
+					},
+					('^', l, _) => Ok(l),
+					_ => Ok(None)
+				}
+			},
+			Expression::Call(name, args) => {
+				let a = args.iter().map(|e| e.dimension_in(bindings)).collect::<Result<Vec::<Option<Dimension>>, DimensionError>>()?;
+				match (name.as_str(), a.as_slice()) {
+					("sqrt", [Some(d)]) => d.sqrt().map(Some),
+					("sqrt", [d]) | ("abs", [d]) | ("sum", [d]) => Ok(*d),
+					("min", [x, y]) | ("max", [x, y]) => same((&args[0], *x), (&args[1], *y)),
+					("if", [_, x, y]) => same((&args[1], *x), (&args[2], *y)),
+					("atan2", [y, x]) => same((&args[0], *y), (&args[1], *x)).map(|_| Some(Dimension::NONE)),
+					// Other functions only accept plain numbers
+					_ => match a.iter().flatten().find(|d| **d != Dimension::NONE) {
+						Some(d) => Err(DimensionError::NotDimensionless(name.clone(), *d)),
+						None => Ok(Some(Dimension::NONE))
+					}
+				}
+			}
+		}
+	}
+
+	/// Whether the expression calls a random function, two equal random expressions having different values.
+	pub fn is_random(&self) -> bool {
+		match self {
+			Expression::Value(_) | Expression::Quantity(..) | Expression::Variable(_) => false,
+			Expression::Negation(e) | Expression::Not(e) => e.is_random(),
+			Expression::Operation(_, left, right) => left.is_random() || right.is_random(),
+			Expression::Call(name, args) => RANDOM_FUNCTIONS.contains(&name.as_str()) || args.iter().any(|a| a.is_random())
+		}
+	}
+
+	/// Calls to `function` in the expression, arguments included.
+	pub fn calls(&self, function: &str) -> Vec::<Expression> {
+		match self {
+			Expression::Value(_) | Expression::Quantity(..) | Expression::Variable(_) => Vec::<Expression>::new(),
+			Expression::Negation(e) | Expression::Not(e) => e.calls(function),
+			Expression::Operation(_, left, right) => {
+				let mut r = left.calls(function);
+				r.append(&mut right.calls(function));
+				r
+			},
+			Expression::Call(name, args) => {
+				let mut r = args.iter().flat_map(|a| a.calls(function)).collect::<Vec::<Expression>>();
+				if name == function {
+					r.push(self.clone());
+				}
+				r
+			}
+		}
+	}
+
+	/// Expression with the variables named in `values` replaced by their expression.
+	pub fn substitute(&self, values: &HashMap::<String, Expression>) -> Expression {
+		use Expression::*;
+
+		match self {
+			Variable(name) => values.get(name).cloned().unwrap_or_else(|| self.clone()),
+			Value(_) | Quantity(..) => self.clone(),
+			Negation(e) => Negation(Box::new(e.substitute(values))),
+			Not(e) => Not(Box::new(e.substitute(values))),
+			Operation(op, left, right) => Operation(*op, Box::new(left.substitute(values)), Box::new(right.substitute(values))),
+			Call(name, args) => Call(name.clone(), args.iter().map(|a| a.substitute(values)).collect())
+		}
+	}
+
+	/// Checks that the expression is consistent and may be used as a quantity of dimension `expected`.
+	pub fn check_dimension(&self, expected: Dimension) -> Result<(), DimensionError> {
+		match self.dimension()? {
+			Some(d) if d != expected && !self.is_plain_number() => Err(DimensionError::Unexpected(expected, d)),
+			_ => Ok(())
+		}
+	}
+
+	/// Whether the expression is made of numbers only, without unit nor variable, besides the
+	/// dimensionless constants. It may stand for a quantity of any dimension, e.g. `400` for a length.
+	pub fn is_plain_number(&self) -> bool {
+		match self {
+			Expression::Value(_) => true,
+			Expression::Quantity(..) => false,
+			Expression::Variable(name) => ["pi", "e"].contains(&name.as_str()),
+			Expression::Negation(e) | Expression::Not(e) => e.is_plain_number(),
+			Expression::Operation(_, left, right) => left.is_plain_number() && right.is_plain_number(),
+			Expression::Call(_, args) => args.iter().all(|a| a.is_plain_number())
+		}
+	}
+
+	/// Writes `self` as an operand of an operator of priority `priority`, in parentheses if needed.
+	/// `tie` operands also need parentheses at equal priority: right operands since operations
+	/// are left associative, except for `^` whose left operands need them instead.
+	fn fmt_operand(&self, f: &mut fmt::Formatter, priority: i32, tie: bool) -> fmt::Result {
+		let parenthesized = match self {
+			Expression::Value(v) | Expression::Quantity(v, _) => *v < 0f32,
+			// Logical not binds tighter than any binary operator
+			Expression::Variable(_) | Expression::Call(..) | Expression::Not(_) => false,
+			Expression::Negation(_) => true,
+			Expression::Operation(op, ..) => {
+				let p = Parser::priority(*op).unwrap_or(0);
+				p < priority || (tie && p == priority)
+			}
+		};
+
+		if parenthesized {write!(f, "({self})")} else {write!(f, "{self}")}
+	}
+}
+
+impl fmt::Display for ParsingError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ParsingError::DivisionByZero => write!(f, "division by zero"),
+			ParsingError::UnknownOperator(op) => write!(f, "unknown operator {op}"),
+			ParsingError::InvalidUnaryOperator(op) => write!(f, "{op} isn't a unary operator"),
+			ParsingError::NoOperator => write!(f, "missing operator"),
+			ParsingError::NotAValue => write!(f, "expected a value"),
+			ParsingError::MissingVariableInContext(name) => write!(f, "unknown variable {name}"),
+			ParsingError::EmptyTokenData => write!(f, "empty expression"),
+			ParsingError::UnknownFunction(name) => write!(f, "unknown function {name}"),
+			ParsingError::WrongArgumentCount(name, count) => write!(f, "{name} doesn't take {count} arguments"),
+			ParsingError::MisplacedComma => write!(f, "misplaced comma"),
+			ParsingError::MisplacedAssignment => write!(f, "misplaced assignment"),
+			ParsingError::RecursiveFunction(name) => write!(f, "{name} calls itself"),
+			ParsingError::FunctionInUse(name, caller) => write!(f, "{name} is called by {caller}"),
+			ParsingError::BuiltinFunction(name) => write!(f, "{name} is a built-in function"),
+			ParsingError::Vector(e) => write!(f, "{e}")
+		}
+	}
+}
+
+/// Pretty-printer writing only the parentheses needed for the expression to parse back the same.
+impl fmt::Display for Expression {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Expression::Value(v) => write!(f, "{v}"),
+			Expression::Quantity(v, d) => write!(f, "{v}[{d}]"),
+			Expression::Variable(name) => write!(f, "{name}"),
+			// Unary minus applies to everything up to the next operator of priority 3 or lower
+			Expression::Negation(e) => {
+				write!(f, "-")?;
+				e.fmt_operand(f, 4, false)
+			},
+			// Logical not only applies to the operand right after it
+			Expression::Not(e) => {
+				write!(f, "!")?;
+				e.fmt_operand(f, 6, false)
+			},
+			Expression::Operation(op, left, right) => {
+				let priority = Parser::priority(*op).unwrap_or(0);
+				left.fmt_operand(f, priority, *op == '^')?;
+				if priority < 4 {write!(f, " {} ", operator_text(*op))?} else {write!(f, "{op}")?}
+				right.fmt_operand(f, priority, *op != '^')
+			},
+			Expression::Call(name, args) => {
+				write!(f, "{name}(")?;
+				for (i, a) in args.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+					write!(f, "{a}")?;
+				}
+				write!(f, ")")
+			}
+		}
+	}
+}
+
+/// Script of `;` separated statements `name = expression`, parsed once. Each statement
+/// may use the names bound by the previous ones.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+	pub bindings: Vec::<(String, Expression)>
+}
+
+impl Program {
+	/// Builds the statements of `tokens`. A statement without assignment binds `default_output`,
+	/// a plain expression being a program of one statement.
+	pub fn build(tokens: &[Token], default_output: &str) -> Result<Self, ParsingError> {
+		let mut bindings = Vec::<(String, Expression)>::new();
+
+		for statement in tokens.split(|t| *t == Token::Semicolon) {
+			let (name, expression) = match statement {
+				[] => continue,
+				[Token::Variable(name), Token::Assignment, expression @ ..] => (name.clone(), expression),
+				expression => (default_output.to_string(), expression)
+			};
+			if expression.contains(&Token::Assignment) {
+				return Err(ParsingError::MisplacedAssignment);
+			}
+			bindings.push((name, Parser::build(&expression.to_vec())?));
+		}
+
+		Ok(Program {bindings})
+	}
+
+	/// Adds the statements of `other` after those of `self`.
+	pub fn append(&mut self, other: Program) {
+		self.bindings.extend(other.bindings);
+	}
+
+	/// Runs the statements in order, returning `context` with every binding added.
+	pub fn evaluate(&self, context: &VariableContext) -> Result<VariableContext, ParsingError> {
+		self.evaluate_with(context, &mut RandomStream::default())
+	}
+
+	/// Runs the statements in order, random functions drawing their numbers from `random`.
+	pub fn evaluate_with(&self, context: &VariableContext, random: &mut RandomStream) -> Result<VariableContext, ParsingError> {
+		let mut values = context.clone();
+		for (name, expression) in self.bindings.iter() {
+			let v = expression.evaluate_with(&values, random)?;
+			values.insert(name.clone(), v);
+		}
+		Ok(values)
+	}
+
+	/// Checks the dimensions of every statement, bound names having the dimension of their expression.
+	pub fn check_dimensions(&self) -> Result<(), DimensionError> {
+		self.dimensions().map(|_| ())
+	}
+
+	/// Checks that the last statement binding `output`, if any, may be used as a quantity of
+	/// dimension `expected`.
+	pub fn check_output(&self, output: &str, expected: Dimension) -> Result<(), DimensionError> {
+		let dimensions = self.dimensions()?;
+		match (self.bindings.iter().rev().find(|(name, _)| name == output), dimensions.get(output)) {
+			(Some((_, expression)), Some(Some(d))) if *d != expected && !expression.is_plain_number() => Err(DimensionError::Unexpected(expected, *d)),
+			_ => Ok(())
+		}
+	}
+
+	/// Dimensions of the names bound by the statements.
+	fn dimensions(&self) -> Result<HashMap::<String, Option<Dimension>>, DimensionError> {
+		let mut bindings = HashMap::<String, Option<Dimension>>::new();
+		for (name, expression) in self.bindings.iter() {
+			let d = expression.dimension_in(&bindings)?;
+			bindings.insert(name.clone(), d);
+		}
+		Ok(bindings)
+	}
+}
+
+impl fmt::Display for Program {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, (name, expression)) in self.bindings.iter().enumerate() {
+			if i > 0 {
+				write!(f, "; ")?;
+			}
+			write!(f, "{name} = {expression}")?;
+		}
+		Ok(())
+	}
+}
+
+/// Function defined by the user, e.g. `spring(d, k) = -k*d`. Its body may use any variable
+/// besides its parameters, and call built-in functions or other user functions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserFunction {
+	pub parameters: Vec::<String>,
+	pub body: Expression
+}
+
+/// Functions defined by the user. Calls to them are inlined in the expressions using them,
+/// which are then evaluated as if written without them.
+#[derive(Debug, Clone, Default)]
+pub struct FunctionLibrary {
+	functions: HashMap::<String, UserFunction>
+}
+
+impl FunctionLibrary {
+	/// Names of the functions, sorted.
+	pub fn names(&self) -> Vec::<&str> {
+		let mut names = self.functions.keys().map(|n| n.as_str()).collect::<Vec::<&str>>();
+		names.sort();
+		names
+	}
+
+	pub fn get(&self, name: &str) -> Option<&UserFunction> {
+		self.functions.get(name)
+	}
+
+	/// Defines or redefines the function `name`. Fails without changing the library when it would
+	/// hide a built-in function, or a function would call itself or call another one with a wrong
+	/// number of arguments.
+	pub fn define(&mut self, name: &str, parameters: Vec::<String>, body: Expression) -> Result<(), ParsingError> {
+		if FUNCTIONS.iter().any(|(n, _)| *n == name) {
+			return Err(ParsingError::BuiltinFunction(name.to_string()));
+		}
+		let mut library = self.clone();
+		library.functions.insert(name.to_string(), UserFunction {parameters, body});
+
+		// Redefining a function may break the ones calling it
+		for (name, function) in library.functions.iter() {
+			library.inline_in(&function.body, &mut vec![name.clone()])?;
+		}
+
+		*self = library;
+		Ok(())
+	}
+
+	/// Removes the function `name`, which must not be called by another function.
+	pub fn remove(&mut self, name: &str) -> Result<(), ParsingError> {
+		if let Some(caller) = self.names().into_iter().find(|n| *n != name && !self.functions[*n].body.calls(name).is_empty()) {
+			return Err(ParsingError::FunctionInUse(name.to_string(), caller.to_string()));
+		}
+		self.functions.remove(name);
+		Ok(())
+	}
+
+	/// `expression` with every call to a user function replaced by its body.
+	pub fn inline(&self, expression: &Expression) -> Result<Expression, ParsingError> {
+		self.inline_in(expression, &mut Vec::<String>::new())
+	}
+
+	/// Inlines every statement of `program`.
+	pub fn inline_program(&self, program: &Program) -> Result<Program, ParsingError> {
+		let bindings = program.bindings.iter()
+			.map(|(name, e)| Ok((name.clone(), self.inline(e)?)))
+			.collect::<Result<Vec::<(String, Expression)>, ParsingError>>()?;
+		Ok(Program {bindings})
+	}
+
+	/// Inlines `expression` within the bodies of the functions of `calls`, which can't be called again.
+	fn inline_in(&self, expression: &Expression, calls: &mut Vec::<String>) -> Result<Expression, ParsingError> {
+		use Expression::*;
+
+		match expression {
+			Value(_) | Quantity(..) | Variable(_) => Ok(expression.clone()),
+			Negation(e) => Ok(Negation(Box::new(self.inline_in(e, calls)?))),
+			Not(e) => Ok(Not(Box::new(self.inline_in(e, calls)?))),
+			Operation(op, left, right) => Ok(Operation(*op, Box::new(self.inline_in(left, calls)?), Box::new(self.inline_in(right, calls)?))),
+			Call(name, args) => {
+				let args = args.iter().map(|a| self.inline_in(a, calls)).collect::<Result<Vec::<Expression>, ParsingError>>()?;
+				let Some(function) = self.functions.get(name) else {
+					return match FUNCTIONS.iter().any(|(n, _)| n == name) {
+						true => Ok(Call(name.clone(), args)),
+						false => Err(ParsingError::UnknownFunction(name.clone()))
+					};
+				};
+				if calls.contains(name) {
+					return Err(ParsingError::RecursiveFunction(name.clone()));
+				}
+				if function.parameters.len() != args.len() {
+					return Err(ParsingError::WrongArgumentCount(name.clone(), args.len()));
+				}
+
+				calls.push(name.clone());
+				let body = self.inline_in(&function.body, calls)?;
+				calls.pop();
+
+				// Arguments are already inlined, so substituting them once is enough
+				Ok(body.substitute(&function.parameters.iter().cloned().zip(args).collect()))
+			}
+		}
+	}
+}
+
 pub struct Parser;
 impl Parser {
 	
-	/// Gate to recursive parsing
-	pub fn parse(tokens: &Vec::<Token>, context: &VariableContext) -> Result<f32, ParsingError> {
-		Self::recursive_parsing(tokens, context, 0usize, tokens.len())
+	/// Builds the expression tree of the tokens without evaluating it.
+	pub fn build(tokens: &Vec::<Token>) -> Result<Expression, ParsingError> {
+		Self::recursive_building(tokens, 0usize, tokens.len())
 	}
 
-	/// Locates the last operation in order of priority and recursively resolves
-	/// the left and right operands to perform a simple Operand Operator Operand
-	/// calculation.
-	fn recursive_parsing(tokens: &Vec::<Token>, context: &VariableContext, start: usize, end: usize) -> Result<f32, ParsingError> {
+	/// Locates the last operation in order of priority and recursively builds
+	/// the left and right operands to make a simple Operand Operator Operand
+	/// node.
+	fn recursive_building(tokens: &Vec::<Token>, start: usize, end: usize) -> Result<Expression, ParsingError> {
 
 		// this block will be executed if user explicitly passes
 		// an empty vector of token as tokens or if we came accross
@@ -48,21 +707,24 @@ impl Parser {
 		match Self::find_least_prior_operator(tokens, start, end) {
 			Ok (op) => {
 				// Split tokens in two at least prior operator position and calls this method recursively on each of those two parts. 
-				match (Self::recursive_parsing(tokens, context, start, op.1), Self::recursive_parsing(tokens, context, op.1 + 1, end)) {
+				match (Self::recursive_building(tokens, start, op.1), Self::recursive_building(tokens, op.1 + 1, end)) {
+					// Like for "(-2)", the left hand side of "(!x)" is only made of opening parentheses
+					(Ok(_), Ok(value)) if op.0 == '!' => {
+						match tokens[start..op.1].iter().all(|t| *t == Token::Parenthesis(true)) {
+							true => Ok(Expression::Not(Box::new(value))),
+							false => Err(ParsingError::InvalidUnaryOperator(op.0))
+						}
+					},
 					(Ok(left_hand), Ok(right_hand)) => {
 						match op.0 {
-							'+' => Ok(left_hand + right_hand),
-							'-' => Ok(left_hand - right_hand),
-							'/' => Ok(left_hand / right_hand),
-							'*' => Ok(left_hand * right_hand),
-							'>' => Ok((left_hand > right_hand) as i32 as f32),
-							'<' => Ok((left_hand < right_hand) as i32 as f32),
+							'+' | '-' | '/' | '*' | '^' | '>' | '<' | '≥' | '≤' | '=' | '≠' | '&' | '|' => Ok(Expression::Operation(op.0, Box::new(left_hand), Box::new(right_hand))),
 							 _  => Err(ParsingError::UnknownOperator(op.0)) 
 						}
 					},
 					(Err(ParsingError::EmptyTokenData), Ok(value)) => {
 						match op.0 {
-							'-' => Ok(-value),
+							'-' => Ok(Expression::Negation(Box::new(value))),
+							'!' => Ok(Expression::Not(Box::new(value))),
 							 _  => Err(ParsingError::InvalidUnaryOperator (op.0))
 						}
 					},
@@ -81,92 +743,118 @@ impl Parser {
 			Err(ParsingError::NoOperator) => {
 				
 				// I) The next piece of code suposes that at this point the tokens are either :
-				//		1) (*[Variable | Value]
-				//      2) [Variable | Value])*
-				//		3) [Variable | Value]
-				//		4) (*
-				// If it is not 1) or 4), then it is 2) or 3) which are both parsed the same way.
-				// If it is 1) or 4), the difference is handled later.
-				/*let mut token_then_only_prths = true; // which is in fact as well true when treating 2) as when treating 3).
-				for i in start..end {
-					if i != start && tokens[i] != Token::Parenthesis(false) {
-						token_then_only_prths = false;
-					}
-				}*/
-				let token_then_only_prths = match tokens[start] {
-					Token::Parenthesis(true) => false,
-					Token::Parenthesis(false) => {
-						println!("Should never happen 1, delete after rigorous testing.");
-						false
-					},
-					_ => true
-				};
-				
-				// Points to the token that contains the value or the variable
-				let contains_value = if token_then_only_prths {
-					&tokens[start]
-				} else {
-					match &tokens[end-1] {
-						Token::Value(_) | Token::Variable(_) => &tokens[end-1],
-						Token::Parenthesis(true) => {
-							return Ok(0f32);
-						},
-						Token::Parenthesis(false) => {
-							// Have Parenthesis [Value | Variable] Parenthesis
-							&tokens[start + 1]
-						},
-						_ => {
-							println!("Should never happen 2... Delete after rigorous testing.");
-							return Err(ParsingError::NotAValue);
-						}
-					}
+				//		1) (*[Variable | Value | Call])*
+				//		2) (*
+				// where the parentheses are leftovers of the splits of the parent calls.
+				// 2) happens for unary operators in parentheses, for instance the left
+				// hand side of "(-2)", which is read as "(0-2)".
+				let Some(first) = (start..end).find(|i| tokens[*i] != Token::Parenthesis(true)) else {
+					return Ok(Expression::Value(0f32));
 				};
 
 				// Unboxing the value
-				match contains_value {
-					Token::Value(val) => Ok(*val),
-					Token::Variable(name) => {
-						match context.get(name) {
-							Some(val) => Ok(*val),
-							None      => Err(ParsingError::MissingVariableInContext(name.clone()))
+				let (expression, last) = match &tokens[first] {
+					Token::Value(val) => match tokens.get(first + 1) {
+						Some(Token::Unit(d)) if first + 1 < end => (Expression::Quantity(*val, *d), first + 1),
+						_ => (Expression::Value(*val), first)
+					},
+					Token::Variable(name) => (Expression::Variable(name.clone()), first),
+					Token::Function(name) => {
+						let (args, last) = Self::build_arguments(tokens, first + 1, end)?;
+
+						match FUNCTIONS.iter().find(|(n, _)| n == name) {
+							Some((_, arity)) if *arity != args.len() => return Err(ParsingError::WrongArgumentCount(name.clone(), args.len())),
+							// User functions are checked by their library when inlined
+							_ => (Expression::Call(name.clone(), args), last)
 						}
-					}
-					_ => Err(ParsingError::NotAValue) // This raises if the assumption I) is false (hopefully, never)
+					},
+					Token::Comma => return Err(ParsingError::MisplacedComma),
+					_ => return Err(ParsingError::NotAValue) // This raises if the assumption I) is false (hopefully, never)
+				};
+
+				// Only closing parentheses may follow, commas being out of a function call otherwise
+				match tokens[last + 1..end].iter().find(|t| **t != Token::Parenthesis(false)) {
+					None => Ok(expression),
+					Some(Token::Comma) => Err(ParsingError::MisplacedComma),
+					Some(_) => Err(ParsingError::NotAValue)
 				}
 			},
 			Err(e) => Err(e)
 		}
 	}
 
-	fn find_least_prior_operator(tokens: &[Token], start: usize, end: usize) -> Result<OperatorPosition, ParsingError> {
+	/// Builds the comma separated arguments of the call whose opening parenthesis is at `open`.
+	/// Also returns the position of the closing parenthesis.
+	fn build_arguments(tokens: &Vec::<Token>, open: usize, end: usize) -> Result<(Vec::<Expression>, usize), ParsingError> {
+		let mut args = Vec::<Expression>::new();
+		let mut argument_start = open + 1;
+		let mut parenthesis_level = 0i32;
+
+		for i in open..end {
+			match tokens[i] {
+				Token::Parenthesis(true) => parenthesis_level += 1i32,
+				Token::Parenthesis(false) => {
+					parenthesis_level -= 1i32;
+					if parenthesis_level == 0i32 {
+						// No argument at all is fine, an empty last argument is not
+						if i > argument_start || !args.is_empty() {
+							args.push(Self::recursive_building(tokens, argument_start, i)?);
+						}
+						return Ok((args, i));
+					}
+				},
+				Token::Comma if parenthesis_level == 1i32 => {
+					args.push(Self::recursive_building(tokens, argument_start, i)?);
+					argument_start = i + 1;
+				},
+				_ => {}
+			}
+		}
 
-		// The higher the number, the higher the priority
-		let mut priorities = HashMap::<char, i32>::new();
-		priorities.insert('>', 0);
-		priorities.insert('<', 0);
-		priorities.insert('+', 1);
-		priorities.insert('-', 1);
-		priorities.insert('*', 2);
-		priorities.insert('/', 2);
+		Err(ParsingError::EmptyTokenData)
+	}
+
+	/// Priority of an operator, the higher the number, the higher the priority.
+	fn priority(operator: char) -> Option<i32> {
+		match operator {
+			'|' => Some(0),
+			'&' => Some(1),
+			'>' | '<' | '≥' | '≤' | '=' | '≠' => Some(2),
+			'+' | '-' => Some(3),
+			'*' | '/' => Some(4),
+			'^' => Some(5),
+			'!' => Some(6),
+			_ => None
+		}
+	}
+
+	fn find_least_prior_operator(tokens: &[Token], start: usize, end: usize) -> Result<OperatorPosition, ParsingError> {
 
 		let mut r = OperatorPosition ('\0', 0usize); // Result variable
 		let mut lowest_prio = 0i32;
 		let mut parenthesis_level = 0i32;
+		// Parenthesis levels of the arguments of the function calls being read,
+		// whose operators belong to the arguments
+		let mut call_levels = Vec::<i32>::new();
 
 		for i in start..end {
 
 			match tokens[i] {
+				Token::Operator(_) if !call_levels.is_empty() => {},
 				Token::Operator(c) => {
 					
-					match priorities.get(&c) {
+					match Self::priority(c) {
 						Some (prio) => {
 							
 							// /!\ Dirty hack /!\
 							// Works as long as no operator has a priority greater than 1000
-							if *prio + parenthesis_level * 1000i32 <= lowest_prio || r.0 == '\0' {
+							let level_prio = prio + parenthesis_level * 1000i32;
+							// The last operator of equal priority is split first for operations to be
+							// left associative, while chains of `^` or of prefix `!` are split at their first one
+							if level_prio < lowest_prio || (level_prio == lowest_prio && !matches!(c, '^' | '!')) || r.0 == '\0' {
 								r.0 = c;
 								r.1 = i;
-								lowest_prio = *prio + parenthesis_level * 1000i32;
+								lowest_prio = level_prio;
 							}
 						},
 						None => {
@@ -174,8 +862,14 @@ impl Parser {
 						}
 					}
 				},
+				Token::Function(_) => call_levels.push(parenthesis_level + 1i32),
 				Token::Parenthesis(true)  => parenthesis_level += 1i32,
-				Token::Parenthesis(false) => parenthesis_level -= 1i32,
+				Token::Parenthesis(false) => {
+					if call_levels.last() == Some(&parenthesis_level) {
+						call_levels.pop();
+					}
+					parenthesis_level -= 1i32;
+				},
 				_ => {}
 			}
 		}
@@ -185,4 +879,132 @@ impl Parser {
 			 _   => Ok(r)
 		}
 	}
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use common_macros::hash_map;
+
+	fn parse(raw: &str) -> Expression {
+		Parser::build(&Tokenizer::tokenize_with_functions(raw, &[]).unwrap()).unwrap()
+	}
+
+	/// Defines `name(parameters) = body` in `functions`, the body calling the functions already defined.
+	fn define(functions: &mut FunctionLibrary, name: &str, parameters: &[&str], body: &str) -> Result<(), ParsingError> {
+		let mut names = functions.names();
+		names.push(name);
+		let body = Parser::build(&Tokenizer::tokenize_with_functions(body, &names).unwrap())?;
+		functions.define(name, parameters.iter().map(|p| p.to_string()).collect(), body)
+	}
+
+	/// Evaluates `raw`, calling the functions of `functions`, with no variables.
+	fn call(functions: &FunctionLibrary, raw: &str) -> Result<f32, ParsingError> {
+		let expression = Parser::build(&Tokenizer::tokenize_with_functions(raw, &functions.names()).unwrap())?;
+		functions.inline(&expression)?.evaluate(&VariableContext::new())
+	}
+
+	#[test]
+	fn simplifies_neutral_operations() {
+		assert_eq!(parse("x - x").simplify(), Expression::Value(0f32));
+		assert_eq!(parse("0*px + 1*py").simplify(), parse("py"));
+		assert_eq!(parse("(px + 0)/1").simplify(), parse("px"));
+		assert_eq!(parse("2*3 + px").simplify(), parse("6 + px"));
+	}
+
+	#[test]
+	fn differentiates() {
+		assert_eq!(parse("px^2").derivative("px").simplify(), parse("2*px"));
+		assert_eq!(parse("3px + py").derivative("px").simplify(), parse("3"));
+		assert_eq!(parse("sin(px)").derivative("px").simplify(), parse("cos(px)"));
+		assert_eq!(parse("py^2").derivative("px").simplify(), Expression::Value(0f32));
+	}
+
+	#[test]
+	fn prints_expressions_which_parse_back_the_same() {
+		for raw in [
+			"a - (b - c)", "(a - b) - c", "a/(b*c)", "a/b*c", "-px^2", "(-px)^2", "2^3^2", "(2^3)^2",
+			"-(px + 1)", "a - (-b)", "a*(-b)", "!(a && b) || c", "a && (b || c)", "if(a < b, -a, b)",
+			"atan2(py, px)", "9.81[m/s^2]*m", "sqrt(px^2 + py^2)/2", "min(a, max(b, c)) >= 1"
+		] {
+			let expression = parse(raw);
+			assert_eq!(parse(&expression.to_string()), expression, "{raw} printed as {expression}");
+		}
+	}
+
+	#[test]
+	fn refuses_recursive_functions() {
+		let mut functions = FunctionLibrary::default();
+		assert!(matches!(define(&mut functions, "f", &["x"], "f(x) + 1"), Err(ParsingError::RecursiveFunction(_))));
+		assert!(functions.names().is_empty());
+
+		define(&mut functions, "a", &["x"], "2x").unwrap();
+		define(&mut functions, "b", &["x"], "a(x) + 1").unwrap();
+		assert!(matches!(define(&mut functions, "a", &["x"], "b(x)"), Err(ParsingError::RecursiveFunction(_))));
+		// The library is left as it was
+		assert_eq!(call(&functions, "b(3)").unwrap(), 7f32);
+	}
+
+	#[test]
+	fn checks_argument_counts() {
+		let mut functions = FunctionLibrary::default();
+		define(&mut functions, "square", &["x"], "x^2").unwrap();
+		assert!(matches!(call(&functions, "square(1, 2)"), Err(ParsingError::WrongArgumentCount(..))));
+		assert!(matches!(define(&mut functions, "f", &["x"], "square(x, 1)"), Err(ParsingError::WrongArgumentCount(..))));
+
+		// Redefining a function can't break the ones calling it
+		define(&mut functions, "g", &["x"], "square(x) + 1").unwrap();
+		assert!(matches!(define(&mut functions, "square", &["x", "y"], "x*y"), Err(ParsingError::WrongArgumentCount(..))));
+		assert_eq!(call(&functions, "g(3)").unwrap(), 10f32);
+	}
+
+	#[test]
+	fn refuses_hiding_builtin_functions() {
+		let mut functions = FunctionLibrary::default();
+		assert!(matches!(define(&mut functions, "sin", &["x"], "x"), Err(ParsingError::BuiltinFunction(_))));
+		assert_eq!(call(&functions, "sin(0)").unwrap(), 0f32);
+	}
+
+	#[test]
+	fn removes_functions_no_other_function_calls() {
+		let mut functions = FunctionLibrary::default();
+		define(&mut functions, "spring", &["d", "k"], "-k*d").unwrap();
+		define(&mut functions, "anchor", &["d"], "spring(d, 2)").unwrap();
+
+		// A force inlines the calls when applied, and keeps working once the function is removed
+		let tokens = Tokenizer::tokenize_with_functions("spring(px - 400, 2)", &functions.names()).unwrap();
+		let force = functions.inline(&Parser::build(&tokens).unwrap()).unwrap();
+
+		assert!(matches!(functions.remove("spring"), Err(ParsingError::FunctionInUse(name, caller)) if name == "spring" && caller == "anchor"));
+		functions.remove("anchor").unwrap();
+		functions.remove("spring").unwrap();
+		assert!(functions.names().is_empty());
+		assert_eq!(force.evaluate(&hash_map!{"px".to_string() => 403f32}).unwrap(), -6f32);
+	}
+
+	#[test]
+	fn skips_dead_branches() {
+		let context = hash_map!{"c".to_string() => 1f32, "a".to_string() => 2f32};
+		let evaluate = |raw: &str| parse(raw).evaluate(&context).unwrap();
+
+		assert_eq!(evaluate("0 && 1/0"), 0f32);
+		assert_eq!(evaluate("1 || 1/0"), 1f32);
+		assert_eq!(evaluate("if(c, a, 1/0)"), 2f32);
+		assert_eq!(evaluate("if(!c, 1/0, a)"), 2f32);
+		// An unknown variable fails the evaluation of any branch it is in
+		assert_eq!(evaluate("0 && unknown"), 0f32);
+		assert_eq!(evaluate("1 || unknown"), 1f32);
+		assert_eq!(evaluate("if(c, a, unknown)"), 2f32);
+		assert!(parse("1 && unknown").evaluate(&context).is_err());
+	}
+
+	#[test]
+	fn compares() {
+		let evaluate = |raw: &str| parse(raw).evaluate(&VariableContext::new()).unwrap();
+
+		assert_eq!(evaluate("1 < 2"), 1f32);
+		assert_eq!(evaluate("2 <= 1"), 0f32);
+		assert_eq!(evaluate("1 + 1 == 2 && 3 != 3"), 0f32);
+		assert_eq!(evaluate("!(1 > 2) || 0"), 1f32);
+	}
+}
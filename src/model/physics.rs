@@ -3,7 +3,11 @@ use std::collections::{HashMap, VecDeque};
 use common_macros::hash_map;
 use super::tokening::Token;
 use super::parsing::ParsingError;
-use super::parsing::Parser;
+use super::parsing::{Expression, FunctionLibrary, Parser, Program, VariableContext};
+use super::vectors::{expand_program, expand_scalar, VECTORS};
+use super::random::{RandomStream, WORLD_STREAM};
+use super::triggers::{Event, Trigger};
+use super::units::{Dimension, DimensionError};
 use std::rc::Rc;
 use std::cell::RefCell;
 
@@ -11,16 +15,281 @@ pub type World = Vec::<Point>;
 
 #[derive(Debug)]
 pub struct Force {
-	pub x: Vec::<Token>,
-	pub y: Vec::<Token>
+	/// Statements whose last `x` and `y` bindings are the components, missing ones being worth 0.
+	pub program: Program,
+	/// Potential energy the force derives from, compiled like the program, `None` when unknown.
+	pub potential: Option<Expression>,
+	/// Texts the force was typed as, kept when its program no longer reads like them.
+	pub source: Option<ForceSource>,
+	/// Whether compiling rewrote the typed expressions, by inlining user functions or expanding vectors
+	rewritten: bool
+}
+
+/// Texts of the X, Y and potential inputs of the force menu, shown again when editing a force
+/// which calls user functions or uses vectors, so that they aren't lost.
+#[derive(Debug, Clone, Default)]
+pub struct ForceSource {
+	pub x: String,
+	pub y: String,
+	pub potential: String
 }
 
 impl Force {
 	pub fn new() -> Self {
 		Force {
-			x: Vec::<Token>::new(),
-			y: Vec::<Token>::new()
+			program: Program::default(),
+			potential: None,
+			source: None,
+			rewritten: false
+		}
+	}
+
+	/// Records the texts the force was typed as, when compiling rewrote them.
+	pub fn written_as(mut self, source: ForceSource) -> Self {
+		if self.rewritten {
+			self.source = Some(source);
+		}
+		self
+	}
+
+	/// Force computed by the statements of `x` followed by those of `y`, their plain expressions
+	/// being respectively the x and y components. Names bound in `x` may be used in `y`.
+	/// A plain vector expression, such as `-0.01(p - vec(400, 300))`, gives both components.
+	/// Calls to the user functions of `functions` are inlined, later redefinitions not changing the force.
+	pub fn from_scripts(x: &[Token], y: &[Token], potential: Vec::<Token>, functions: &FunctionLibrary) -> Result<Self, ParsingError> {
+		let mut program = Program::build(x, "x")?;
+		program.append(Program::build(y, "y")?);
+		let compiled = expand_program(&functions.inline_program(&program)?, &["x", "y"]).map_err(ParsingError::Vector)?;
+
+		let written = match potential.is_empty() {
+			true => None,
+			false => Some(Parser::build(&potential)?)
+		};
+		let potential = written.as_ref().map(|u| Self::compile_potential(u, functions)).transpose()?;
+
+		Ok(Force {
+			rewritten: compiled != program || potential != written,
+			program: compiled,
+			potential,
+			source: None
+		})
+	}
+
+	/// Force deriving from the potential energy `potential`: x = -dU/dpx and y = -dU/dpy.
+	pub fn from_potential(potential: Vec::<Token>, functions: &FunctionLibrary) -> Result<Self, ParsingError> {
+		let u = Self::compile_potential(&Parser::build(&potential)?, functions)?;
+		let component = |variable: &str| Expression::Negation(Box::new(u.derivative(variable))).simplify();
+
+		Ok(Force {
+			program: Program {bindings: vec![("x".to_string(), component("px")), ("y".to_string(), component("py"))]},
+			potential: Some(u),
+			source: None,
+			// The components are derived, rather than typed
+			rewritten: false
+		})
+	}
+
+	/// `potential` with its user function calls inlined and its vectors expanded.
+	fn compile_potential(potential: &Expression, functions: &FunctionLibrary) -> Result<Expression, ParsingError> {
+		expand_scalar(&functions.inline(potential)?).map_err(ParsingError::Vector)
+	}
+
+	/// Checks that the expressions of the program are dimensionally consistent. Components which
+	/// aren't forces, such as the unit-less `(400 - px)/100`, still apply, and are described by
+	/// the returned warnings.
+	pub fn check_dimensions(&self) -> Result<Vec::<String>, DimensionError> {
+		self.program.check_dimensions()?;
+		Ok(["x", "y"].into_iter()
+			.filter_map(|c| self.program.check_output(c, Dimension::FORCE).err().map(|e| format!("{} component {e}", c.to_uppercase())))
+			.collect())
+	}
+
+	/// Evaluates both components of the force, running its whole program once.
+	pub fn evaluate(&self, context: &VariableContext, random: &mut RandomStream) -> Result<Vector2, ParsingError> {
+		let values = self.program.evaluate_with(context, random)?;
+		let component = |name: &str| values.get(name).copied().unwrap_or(0f32);
+
+		Ok(Vector2::new(component("x"), component("y")))
+	}
+
+	/// Calls to `sum` in the program and the potential, to be measured before evaluating the force.
+	pub fn sums(&self) -> Vec::<Expression> {
+		let mut r = self.program.bindings.iter()
+			.flat_map(|(_, e)| e.calls("sum"))
+			.collect::<Vec::<Expression>>();
+		if let Some(u) = &self.potential {
+			r.append(&mut u.calls("sum"));
 		}
+		r
+	}
+
+	/// Evaluates the potential energy of the force, `None` if it has no potential.
+	pub fn evaluate_potential(&self, context: &VariableContext) -> Result<Option<f32>, ParsingError> {
+		self.potential.as_ref().map(|u| u.evaluate(context)).transpose()
+	}
+}
+
+/// World-level state shared by every point.
+#[derive(Debug, Default)]
+pub struct Environment {
+	/// Named values usable in every force expression
+	pub parameters: VariableContext,
+	/// Simulation time `t`, in steps
+	pub time: f32,
+	/// Seed of the random numbers drawn by expressions
+	pub seed: u64,
+	/// Number of points created so far, giving each point its own random stream
+	pub created_points: u64,
+	/// Collective quantities of the world, measured once per step
+	pub aggregates: Aggregates,
+	/// Variables carried by every point
+	pub state: StateVariables,
+	/// Rules run when their condition becomes true
+	pub triggers: Vec::<Trigger>,
+	/// Last value of the conditions of the triggers checked on the world, by trigger text
+	pub world_conditions: HashMap::<String, bool>
+}
+
+impl Environment {
+	/// Checks the triggers which don't depend on a point, once per step.
+	pub fn check_triggers(&mut self) -> Vec::<Event> {
+		let mut context = self.parameters.clone();
+		context.extend(self.aggregates.values.clone());
+		context.extend(hash_map!{
+			"t".to_string() => self.time,
+			"dt".to_string() => 1f32
+		});
+		let mut random = RandomStream::new(self.seed, WORLD_STREAM, self.time as u64);
+		let state = self.state.names();
+
+		let mut events = Vec::<Event>::new();
+		for trigger in self.triggers.iter().filter(|t| t.is_global(&state)) {
+			let condition = match trigger.condition(&context, &mut random) {
+				Ok(c) => c,
+				Err(e) => {
+					println!("Error on trigger \"{}\" : {e}.", trigger.source);
+					continue;
+				}
+			};
+			// A condition already true at its first check doesn't fire
+			if self.world_conditions.insert(trigger.source.clone(), condition) != Some(false) || !condition {
+				continue;
+			}
+			match trigger.run(&context, &mut random) {
+				Ok((_, mut e)) => events.append(&mut e),
+				Err(e) => println!("Error on trigger \"{}\" : {e}.", trigger.source)
+			}
+		}
+		let triggers = &self.triggers;
+		self.world_conditions.retain(|source, _| triggers.iter().any(|t| t.source == *source && t.is_global(&state)));
+		events
+	}
+}
+
+/// Variables carried by every point, e.g. `charge` or `age`, each point having its own values.
+#[derive(Debug, Default, Clone)]
+pub struct StateVariables {
+	/// Statements giving the initial values of the variables, which are the names they bind
+	pub initial: Program,
+	/// Statements run on every point after each step, the variables they bind being updated
+	pub update: Program
+}
+
+impl StateVariables {
+	pub fn names(&self) -> Vec::<&str> {
+		self.initial.bindings.iter().map(|(name, _)| name.as_str()).collect()
+	}
+
+	/// Checks that the variables may be used as state variables, and that the update statements
+	/// only bind variables the initial statements declare, as other names wouldn't be updated.
+	pub fn check(&self) -> Result<(), String> {
+		let names = self.names();
+		for (name, _) in self.initial.bindings.iter().chain(self.update.bindings.iter()) {
+			if name.is_empty() || Point::is_reserved(name) {
+				return Err(format!("\"{name}\" can't be used as a state variable"));
+			}
+			if !names.contains(&name.as_str()) {
+				return Err(format!("\"{name}\" is updated but not declared by the initial statements"));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Collective quantities of the world available to every point.
+#[derive(Debug, Default)]
+pub struct Aggregates {
+	/// Center of mass `meanx` and `meany`, number of points `count`, and the sums used by
+	/// forces named as written, e.g. `sum(vx)`
+	pub values: VariableContext,
+	/// Position of every point along with its creation index, to find the nearest neighbours
+	pub positions: Vec::<(u64, Vector2)>,
+	/// Distance from each point to its nearest neighbour, by creation index
+	pub nearest: HashMap::<u64, f32>
+}
+
+impl Aggregates {
+	/// Variables measured on the whole world, besides sums.
+	pub const VARIABLES: [&str; 4] = ["meanx", "meany", "count", "nearest_dist"];
+
+	/// Measures the collective quantities of `world` and the sums `sums`, calls to `sum`
+	/// whose argument is evaluated on every point.
+	pub fn measure(world: &World, sums: &[Expression]) -> Self {
+		let mass = world.iter().map(|p| p.mass).sum::<f32>();
+		let center = match mass {
+			0f32 => Vector2::zero(),
+			mass => world.iter().fold(Vector2::zero(), |c, p| c + p.position * p.mass) / mass
+		};
+
+		let mut values = hash_map!{
+			"meanx".to_string() => center.x,
+			"meany".to_string() => center.y,
+			"count".to_string() => world.len() as f32
+		};
+
+		// Each pair of points is compared once
+		let mut nearest = world.iter().map(|p| (p.index, f32::INFINITY)).collect::<HashMap::<u64, f32>>();
+		for (i, p) in world.iter().enumerate() {
+			for q in world[i + 1..].iter() {
+				let d = p.position.distance_to(q.position);
+				for index in [p.index, q.index] {
+					if let Some(n) = nearest.get_mut(&index) {
+						*n = n.min(d);
+					}
+				}
+			}
+		}
+
+		for call in sums.iter() {
+			let Expression::Call(_, args) = call else {continue;};
+			if values.contains_key(&call.to_string()) {
+				continue;
+			}
+			match world.iter().map(|p| args[0].evaluate(&p.context())).sum::<Result<f32, ParsingError>>() {
+				Ok(total) => {values.insert(call.to_string(), total);},
+				// Forces using the sum then report it as missing
+				Err(e) => println!("Error while measuring {call} : {e}.")
+			}
+		}
+
+		Aggregates {
+			values,
+			positions: world.iter().map(|p| (p.index, p.position)).collect(),
+			nearest
+		}
+	}
+
+	/// Distance from `position` to the nearest point other than the point of index `index`,
+	/// infinite when there is none. Points measured with the world only look up their distance,
+	/// while probes and points created since the measure search every point.
+	fn nearest_distance(&self, position: Vector2, index: Option::<u64>) -> f32 {
+		if let Some(d) = index.and_then(|i| self.nearest.get(&i)) {
+			return *d;
+		}
+		self.positions.iter()
+			.filter(|(i, _)| Some(*i) != index)
+			.map(|(_, p)| p.distance_to(position))
+			.fold(f32::INFINITY, f32::min)
 	}
 }
 
@@ -29,35 +298,218 @@ pub enum PointStyle {
 	Cross
 }
 
+/// How trail segments are colored.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TrailColoring {
+	/// From blue (old) to orange (recent).
+	Time,
+	/// From blue (slow) to orange (fast).
+	Speed,
+	/// One color per point.
+	Point
+}
+
+/// Trail drawing settings shared by every point.
+#[derive(Clone, Copy, PartialEq)]
+pub struct TrailOptions {
+	pub coloring: TrailColoring,
+	/// Width in screen pixels of the most recent segment.
+	pub thickness: f32,
+	/// Whether older segments get thinner and more transparent. Persistent trails never fade.
+	pub fade: bool
+}
+
+/// Vectors drawn as arrows on a point by `Point::draw_arrows`.
+#[derive(Clone, Copy)]
+pub struct ArrowOptions {
+	pub speed: bool,
+	pub acceleration: bool,
+	pub forces: bool
+}
+
 pub struct Point {
 	// Simulation data
 	position: Vector2,
 	speed: Vector2,
 	acceleration: Vector2,
+	mass: f32,
 	forces: Rc::<RefCell::<HashMap::<String, Force>>>,
+	// World-level parameters, time and seed, shared by every point
+	environment: Rc::<RefCell::<Environment>>,
+	// Creation index of the point, keying its random stream
+	index: u64,
+	// Values of the state variables
+	state: VariableContext,
+	// Last value of the conditions of the triggers checked on this point, by trigger text
+	conditions: HashMap::<String, bool>,
 
 	// Drawing data
-	trail: Option::<VecDeque::<Vector2>>
+	color: Color,
+	// Recorded positions along with the speed norm at that time
+	trail: Option::<VecDeque::<(Vector2, f32)>>,
+	// None means the trail is persistent and never shortened
+	trail_length: Option::<usize>,
+	// Number of persistent trail samples already drawn by `draw_new_trace_segments`
+	trace_drawn: usize
 }
 
 impl  Point  {
 
-	const TRAIL_LENGTH: usize = 100;
+	pub const DEFAULT_TRAIL_LENGTH: usize = 100;
+	/// Variables of every point, which parameters can't shadow.
+	pub const VARIABLES: [&str; 7] = ["px", "py", "vx", "vy", "ax", "ay", "m"];
+	const PICK_RADIUS: f32 = 10f32;
+	// Arrows lengths are the distance travelled in SPEED_ARROW_SCALE steps at current speed
+	// and the speed gained in ACCELERATION_ARROW_SCALE steps at current acceleration.
+	const SPEED_ARROW_SCALE: f32 = 10f32;
+	const ACCELERATION_ARROW_SCALE: f32 = 100f32;
+	// Speed norm (world units per step) colored halfway in the speed trail coloring
+	const TRAIL_SPEED_SCALE: f32 = 5f32;
+	// Number of samples for a persistent time-colored trail to go from blue to orange
+	const TRACE_COLOR_PERIOD: usize = 300;
+
+	/// Point at rest at `position`, its state variables taking their initial value.
+	pub fn new(position: Vector2, forces: Rc::<RefCell::<HashMap::<String, Force>>>, environment: Rc::<RefCell::<Environment>>) -> Point {
+		let index = environment.borrow().created_points;
+		environment.borrow_mut().created_points += 1;
 
-	pub fn new(position: Vector2, forces: Rc::<RefCell::<HashMap::<String, Force>>>) -> Point {
-		Point {
+		let mut point = Point {
 			position,
 			speed: Vector2::zero(),
 			acceleration: Vector2::zero(),
+			mass: 1f32,
 			forces,
-			trail: None
-		}
+			environment,
+			index,
+			state: VariableContext::new(),
+			conditions: HashMap::new(),
+			color: Color::BLACK,
+			trail: None,
+			trail_length: Some(Self::DEFAULT_TRAIL_LENGTH),
+			trace_drawn: 0
+		};
+		point.initialize_state();
+		point
+	}
+
+	/// Whether `name` is a variable of every context, which parameters and state variables can't
+	/// take. `x` and `y` are the outputs of force scripts.
+	pub fn is_reserved(name: &str) -> bool {
+		Self::VARIABLES.contains(&name) || Aggregates::VARIABLES.contains(&name) || VECTORS.contains(&name) || ["t", "dt", "x", "y"].contains(&name)
 	}
 
 	pub fn position(&self) -> Vector2 {
 		self.position
 	}
 
+	pub fn speed(&self) -> Vector2 {
+		self.speed
+	}
+
+	pub fn acceleration(&self) -> Vector2 {
+		self.acceleration
+	}
+
+	pub fn mass(&self) -> f32 {
+		self.mass
+	}
+
+	/// Moves the point without affecting its speed. The trail is cleared so that
+	/// no line is drawn between the old and the new position.
+	pub fn set_position(&mut self, position: Vector2) {
+		self.position = position;
+		if let Some(t) = &mut self.trail {
+			t.clear();
+		}
+	}
+
+	pub fn set_speed(&mut self, speed: Vector2) {
+		self.speed = speed;
+	}
+
+	pub fn set_mass(&mut self, mass: f32) {
+		self.mass = mass;
+	}
+
+	pub fn state(&self) -> &VariableContext {
+		&self.state
+	}
+
+	/// Sets the state variable `name`, which must be one of the state variables of the world.
+	pub fn set_state(&mut self, name: &str, value: f32) -> Result<(), String> {
+		match self.state.get_mut(name) {
+			Some(v) => {
+				*v = value;
+				Ok(())
+			},
+			None => Err(format!("\"{name}\" is not a state variable"))
+		}
+	}
+
+	/// Gives the state variables the point doesn't carry yet their initial value, and forgets
+	/// those which aren't state variables anymore.
+	pub fn initialize_state(&mut self) {
+		let values = self.environment.borrow().state.initial.evaluate_with(&self.context(), &mut self.random_stream());
+		match values {
+			Ok(values) => {
+				let environment = self.environment.borrow();
+				let names = environment.state.names();
+				self.state.retain(|name, _| names.contains(&name.as_str()));
+				for name in names {
+					if !self.state.contains_key(name) {
+						self.state.insert(name.to_string(), values.get(name).copied().unwrap_or(0f32));
+					}
+				}
+			},
+			Err(e) => println!("Error while initializing state : {e}.")
+		}
+	}
+
+	pub fn is_trail_visible(&self) -> bool {
+		self.trail.is_some()
+	}
+
+	/// Color of the point's trail with `TrailColoring::Point`.
+	pub fn set_color(&mut self, color: Color) {
+		self.color = color;
+	}
+
+	pub fn trail_length(&self) -> Option::<usize> {
+		self.trail_length
+	}
+
+	/// Sets the number of recorded trail positions, `None` keeping every position forever.
+	pub fn set_trail_length(&mut self, length: Option::<usize>) {
+		self.trail_length = length;
+		if let (Some(t), Some(l)) = (&mut self.trail, length) {
+			while t.len() > l {
+				t.pop_front();
+			}
+		}
+		self.invalidate_trace();
+	}
+
+	/// Whether the trail is visible and never shortened. Such trails are drawn incrementally
+	/// by `draw_new_trace_segments` rather than by `draw`.
+	pub fn has_persistent_trail(&self) -> bool {
+		self.trail.is_some() && self.trail_length.is_none()
+	}
+
+	/// Whether `position` is close enough to the point to grab it with the mouse.
+	/// `zoom` is the number of screen pixels per world unit, so that the grab radius
+	/// stays the same on screen.
+	pub fn is_under(&self, position: Vector2, zoom: f32) -> bool {
+		self.position.distance_to(position) <= Self::PICK_RADIUS / zoom
+	}
+
+	/// Moves the point as if held by the user: the trail keeps being recorded and
+	/// the point stops moving by itself.
+	pub fn drag_to(&mut self, position: Vector2) {
+		self.record_trail();
+		self.position = position;
+		self.speed = Vector2::zero();
+	}
+
 	pub fn set_trail_visibility(&mut self, b: bool) {
 		match &mut self.trail {
 			Some(t) =>
@@ -65,67 +517,211 @@ impl  Point  {
 				else {self.trail = None;}
 			None => 
 				if b {
-					self.trail = Some(VecDeque::<Vector2>::new());
+					self.trail = Some(VecDeque::<(Vector2, f32)>::new());
 				}
 		}
+		self.invalidate_trace();
 	}
 
-	pub fn simulate(&mut self) {
-		
+	fn record_trail(&mut self) {
 		if let Some(t) = &mut self.trail {
 			let mut should_push = true;
-			if let Some(l) = t.iter().last() {
+			if let Some((l, _)) = t.iter().last() {
 				if *l == self.position {
 					should_push = false;
 				}
 			}
 
-			if should_push {t.push_back(self.position);}
+			if should_push {t.push_back((self.position, self.speed.length()));}
 			
-			if t.len() > Self::TRAIL_LENGTH {
-				t.pop_front();
+			if let Some(length) = self.trail_length {
+				if t.len() > length {
+					t.pop_front();
+				}
 			}
 		}
+	}
 
-		self.position += self.speed;
+	/// Advances the point by one step, returning the events of the triggers it fired.
+	pub fn simulate(&mut self) -> Vec::<Event> {
 		
-		let context = hash_map!{
-			"px".to_string() => self.position.x,
-			"py".to_string() => self.position.y,
-			"vx".to_string() => self.speed.x,
-			"vy".to_string() => self.speed.y,
-			"ax".to_string() => self.acceleration.x,
-			"ay".to_string() => self.acceleration.y
-		};
+		self.record_trail();
+
+		self.position += self.speed;
 
 		// Summing into this variable in order to be able to access current acceleration
 		// rather than access a mid-sumation temporary acceleration 
 		let mut new_acceleration = Vector2::zero();
 		
 		// Summing forces
-		for force in self.forces.borrow().iter() {
-			new_acceleration += Vector2::new(
-				match Parser::parse(&force.1.x, &context) {
-					Ok(v) => v,
-					Err(ParsingError::EmptyTokenData) => 0f32,
-					Err(e) => {
-						println!("Error while summing x forces : {e:?}.");
-						0f32
+		let mut random = self.random_stream();
+		for (_, force) in self.evaluate_forces(&self.context(), &mut random) {
+			new_acceleration += force;
+		}
+		self.acceleration = new_acceleration / self.mass;
+		
+		self.speed += self.acceleration;
+
+		// The state update keeps drawing from the stream of the forces
+		let values = self.environment.borrow().state.update.evaluate_with(&self.context(), &mut random);
+		match values {
+			Ok(values) => for (name, v) in self.state.iter_mut() {
+				if let Some(new) = values.get(name) {
+					*v = *new;
+				}
+			},
+			Err(e) => println!("Error while updating state : {e}.")
+		}
+
+		self.check_triggers(&mut random)
+	}
+
+	/// Checks the triggers depending on this point, running the actions of those whose condition
+	/// just became true. A condition already true at its first check, e.g. on a new point,
+	/// doesn't fire.
+	fn check_triggers(&mut self, random: &mut RandomStream) -> Vec::<Event> {
+		let environment = Rc::clone(&self.environment);
+		let environment = environment.borrow();
+		let state = environment.state.names();
+
+		let mut events = Vec::<Event>::new();
+		for trigger in environment.triggers.iter().filter(|t| !t.is_global(&state)) {
+			let context = self.context();
+			let condition = match trigger.condition(&context, random) {
+				Ok(c) => c,
+				Err(e) => {
+					println!("Error on trigger \"{}\" : {e}.", trigger.source);
+					continue;
+				}
+			};
+			if self.conditions.insert(trigger.source.clone(), condition) != Some(false) || !condition {
+				continue;
+			}
+			match trigger.run(&context, random) {
+				Ok((values, mut e)) => {
+					if let Err(e) = self.assign(&values) {
+						println!("Error on trigger \"{}\" : {e}.", trigger.source);
 					}
+					events.append(&mut e);
 				},
-				match Parser::parse(&force.1.y, &context) {
+				Err(e) => println!("Error on trigger \"{}\" : {e}.", trigger.source)
+			}
+		}
+		self.conditions.retain(|source, _| environment.triggers.iter().any(|t| t.source == *source && !t.is_global(&state)));
+		events
+	}
+
+	/// Sets the variables of the point and the state variables among `values`, other names
+	/// being ignored. The trail is kept, the point moving as part of the simulation.
+	/// A mass which isn't strictly positive is left out, like in the point menu, and reported.
+	fn assign(&mut self, values: &VariableContext) -> Result<(), String> {
+		let mut r = Ok(());
+		for (name, v) in values.iter() {
+			match name.as_str() {
+				"px" => self.position.x = *v,
+				"py" => self.position.y = *v,
+				"vx" => self.speed.x = *v,
+				"vy" => self.speed.y = *v,
+				"m" if *v > 0f32 => self.mass = *v,
+				"m" => r = Err(format!("mass must be strictly positive, got {v}")),
+				_ => if let Some(s) = self.state.get_mut(name) {
+					*s = *v;
+				}
+			}
+		}
+		r
+	}
+
+	/// Variables available to force expressions evaluated on this point, parameters, time `t`,
+	/// aggregates and state variables included.
+	/// Read at each evaluation, so that changing a parameter affects the running simulation.
+	pub fn context(&self) -> VariableContext {
+		Self::build_context(self.position, self.speed, self.acceleration, self.mass, &self.environment.borrow(), Some(self.index), &self.state)
+	}
+
+	/// Context of a unit mass probe at rest at the given position, used to preview forces.
+	/// Every point is one of its neighbours, and its state variables have their initial value.
+	pub fn probe_context(position: Vector2, environment: &Environment) -> VariableContext {
+		let context = Self::build_context(position, Vector2::zero(), Vector2::zero(), 1f32, environment, None, &VariableContext::new());
+		match environment.state.initial.evaluate(&context) {
+			Ok(context) => context,
+			Err(_) => context
+		}
+	}
+
+	fn build_context(position: Vector2, speed: Vector2, acceleration: Vector2, mass: f32, environment: &Environment, index: Option::<u64>, state: &VariableContext) -> VariableContext {
+		let mut context = environment.parameters.clone();
+		context.extend(environment.aggregates.values.clone());
+		context.extend(state.clone());
+		context.extend(hash_map!{
+			"t".to_string() => environment.time,
+			// Duration of a step
+			"dt".to_string() => 1f32,
+			"nearest_dist".to_string() => environment.aggregates.nearest_distance(position, index),
+			"px".to_string() => position.x,
+			"py".to_string() => position.y,
+			"vx".to_string() => speed.x,
+			"vy".to_string() => speed.y,
+			"ax".to_string() => acceleration.x,
+			"ay".to_string() => acceleration.y,
+			"m".to_string() => mass
+		});
+		context
+	}
+
+	/// Random numbers drawn by the forces of this point during the current step.
+	fn random_stream(&self) -> RandomStream {
+		let environment = self.environment.borrow();
+		RandomStream::new(environment.seed, self.index, environment.time as u64)
+	}
+
+	/// Evaluates every force on this point, sorted by force name. Forces draw their random
+	/// numbers in that order, so that evaluating them again during a step gives the same result.
+	pub fn force_contributions(&self) -> Vec::<(String, Vector2)> {
+		self.evaluate_forces(&self.context(), &mut self.random_stream())
+	}
+
+	fn evaluate_forces(&self, context: &VariableContext, random: &mut RandomStream) -> Vec::<(String, Vector2)> {
+		let forces = self.forces.borrow();
+		let mut names = forces.keys().collect::<Vec::<&String>>();
+		names.sort();
+
+		let mut r = Vec::<(String, Vector2)>::new();
+		for name in names {
+			r.push((
+				name.clone(),
+				match forces[name].evaluate(context, random) {
 					Ok(v) => v,
-					Err(ParsingError::EmptyTokenData) => 0f32,
 					Err(e) => {
-						println!("Error while summing y forces : {e:?}.");
-						0f32
+						println!("Error while summing forces : {e}.");
+						Vector2::zero()
 					}
 				}
-			);
+			));
 		}
-		self.acceleration = new_acceleration;
-		
-		self.speed += self.acceleration;
+
+		r
+	}
+
+	/// Sum of the potential energies of the forces that have one, evaluated on this point, along
+	/// with whether every force has a potential.
+	pub fn potential_energy(&self) -> (f32, bool) {
+		let context = self.context();
+
+		let mut energy = 0f32;
+		let mut complete = true;
+		for force in self.forces.borrow().values() {
+			match force.evaluate_potential(&context) {
+				Ok(Some(v)) => energy += v,
+				Ok(None) => complete = false,
+				Err(e) => {
+					println!("Error while summing potentials : {e}.");
+					complete = false;
+				}
+			}
+		}
+
+		(energy, complete)
 	}
 
 	/*
@@ -140,39 +736,297 @@ impl  Point  {
 	}
 	*/
 
-	pub fn draw(&mut self, style: PointStyle, handle: &mut RaylibDrawHandle) {
+	/// Draws the point in world coordinates. Sizes are divided by `zoom` (screen pixels
+	/// per world unit) so that the point looks the same at any zoom level.
+	/// Persistent trails are not drawn here, see `draw_new_trace_segments`.
+	pub fn draw(&mut self, style: PointStyle, trail_options: TrailOptions, zoom: f32, handle: &mut impl RaylibDraw) {
 	
-		if let Some(t) = &mut self.trail {
+		if let (Some(t), Some(_)) = (&self.trail, self.trail_length) {
 			if t.len() > 0 {
 				for i in 0..t.len()-1 {
 					let prop = (i as f32)/(t.len() as f32);
-					let value_prop = (255f32 * prop) as u8;
+					let (color, width) = self.trail_segment_style(trail_options, i, prop);
 
-					handle.draw_line_ex(t[i], t[i+1], 10f32 * prop, Color::new(value_prop, value_prop/2, 255 - value_prop, value_prop));
+					handle.draw_line_ex(t[i].0, t[i+1].0, width / zoom, color);
 				}
 			}
 		
 		}
 
 		match style {
-			PointStyle::Circle => handle.draw_circle_v(self.position, 5f32, Color::BLACK),
+			PointStyle::Circle => handle.draw_circle_v(self.position, 5f32 / zoom, Color::BLACK),
 			PointStyle::Cross => {
-				const CROSS_SIZE: f32 = 5f32;
-				handle.draw_line_ex(self.position - Vector2::new(CROSS_SIZE, CROSS_SIZE), self.position + Vector2::new(CROSS_SIZE, CROSS_SIZE), 3f32, Color::RED);
-				handle.draw_line_ex(self.position - Vector2::new(-CROSS_SIZE, CROSS_SIZE), self.position + Vector2::new(-CROSS_SIZE, CROSS_SIZE), 3f32, Color::RED);
+				let cross_size = 5f32 / zoom;
+				handle.draw_line_ex(self.position - Vector2::new(cross_size, cross_size), self.position + Vector2::new(cross_size, cross_size), 3f32 / zoom, Color::RED);
+				handle.draw_line_ex(self.position - Vector2::new(-cross_size, cross_size), self.position + Vector2::new(-cross_size, cross_size), 3f32 / zoom, Color::RED);
+			}
+		}
+	}
+
+	/// Draws the persistent trail segments recorded since the last call, so that a layer that is
+	/// never cleared ends up holding the whole trail. Returns false, drawing nothing, if the trail
+	/// was cleared since: the layer then has to be cleared and `reset_trace` called on every point.
+	pub fn draw_new_trace_segments(&mut self, trail_options: TrailOptions, zoom: f32, handle: &mut impl RaylibDraw) -> bool {
+		if self.trace_drawn > self.trail.as_ref().map_or(0, |t| t.len()) {
+			return false;
+		}
+		if !self.has_persistent_trail() {
+			return true;
+		}
+		let Some(t) = &self.trail else {return true;};
+
+		for i in self.trace_drawn.max(1)..t.len() {
+			let prop = (i % (2 * Self::TRACE_COLOR_PERIOD)) as f32 / Self::TRACE_COLOR_PERIOD as f32;
+			// Back and forth between both ends of the gradient
+			let prop = 1f32 - (prop - 1f32).abs();
+			let (color, width) = self.trail_segment_style(TrailOptions {fade: false, ..trail_options}, i, prop);
+
+			handle.draw_line_ex(t[i-1].0, t[i].0, width / zoom, color);
+		}
+		self.trace_drawn = t.len();
+		true
+	}
+
+	// Makes the next `draw_new_trace_segments` call report that what was drawn is outdated
+	fn invalidate_trace(&mut self) {
+		if self.trace_drawn > 0 {
+			self.trace_drawn = usize::MAX;
+		}
+	}
+
+	/// Forgets which persistent trail segments were drawn, for them to be drawn again.
+	pub fn reset_trace(&mut self) {
+		self.trace_drawn = 0;
+	}
+
+	/// Color and screen width of the trail segment starting at sample `i`,
+	/// `age_prop` going from 0 for the oldest sample to 1 for the most recent one.
+	fn trail_segment_style(&self, options: TrailOptions, i: usize, age_prop: f32) -> (Color, f32) {
+		let gradient = |prop: f32| {
+			let value_prop = (255f32 * prop.clamp(0f32, 1f32)) as u8;
+			Color::new(value_prop, value_prop/2, 255 - value_prop, 255)
+		};
+		let mut color = match options.coloring {
+			TrailColoring::Time => gradient(age_prop),
+			TrailColoring::Speed => {
+				let speed = self.trail.as_ref().map_or(0f32, |t| t[i].1);
+				gradient(1f32 - (-speed / Self::TRAIL_SPEED_SCALE).exp2())
+			},
+			TrailColoring::Point => self.color
+		};
+
+		if options.fade {
+			color.a = (255f32 * age_prop) as u8;
+			(color, options.thickness * age_prop)
+		}
+		else {
+			(color, options.thickness)
+		}
+	}
+
+	/// Draws the speed, the acceleration and the contribution of each force to the acceleration
+	/// as arrows starting from the point. Each force gets its own color and is labeled with its name.
+	pub fn draw_arrows(&self, options: ArrowOptions, zoom: f32, handle: &mut impl RaylibDraw) {
+		if options.forces {
+			for (i, (name, force)) in self.force_contributions().iter().enumerate() {
+				// Golden angle hue steps keep neighbouring forces colors apart
+				let color = Color::color_from_hsv((i as f32 * 137.5f32) % 360f32, 0.8f32, 0.8f32);
+				let tip = self.position + *force / self.mass * Self::ACCELERATION_ARROW_SCALE;
+
+				draw_arrow(self.position, tip, zoom, color, handle);
+				handle.draw_text(name, tip.x as i32, tip.y as i32, ((12f32 / zoom) as i32).max(1), color);
 			}
 		}
+		if options.acceleration {
+			draw_arrow(self.position, self.position + self.acceleration * Self::ACCELERATION_ARROW_SCALE, zoom, Color::RED, handle);
+		}
+		if options.speed {
+			draw_arrow(self.position, self.position + self.speed * Self::SPEED_ARROW_SCALE, zoom, Color::BLUE, handle);
+		}
 	}
 
 	fn only_contains_valid_variables(tokens: &Vec::<Token>) -> bool {
-		const ACCEPTED_VARIABLES:[&str; 6]  = ["px", "py", "vx", "vy", "ax", "ay"];
 		for token in tokens {
 			if let Token::Variable(name) = token {
-				if !ACCEPTED_VARIABLES.contains(&name.as_str()) {
+				if !Self::VARIABLES.contains(&name.as_str()) {
 					return false;
 				}
 			}
 		}
 		true
 	}
-}
\ No newline at end of file
+}
+
+/// Draws an arrow from `from` to `to` whose head keeps the same on-screen size at any zoom.
+pub fn draw_arrow(from: Vector2, to: Vector2, zoom: f32, color: Color, handle: &mut impl RaylibDraw) {
+	const HEAD_SIZE: f32 = 8f32;
+
+	let length = from.distance_to(to);
+	if length * zoom < 1f32 {
+		return;
+	}
+
+	let direction = (to - from) / length;
+	let normal = Vector2::new(-direction.y, direction.x);
+	let head = HEAD_SIZE / zoom;
+
+	handle.draw_line_ex(from, to, 2f32 / zoom, color);
+	handle.draw_line_ex(to, to - direction * head + normal * head / 2f32, 2f32 / zoom, color);
+	handle.draw_line_ex(to, to - direction * head - normal * head / 2f32, 2f32 / zoom, color);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::tokening::Tokenizer;
+
+	fn tokens(raw: &str) -> Vec::<Token> {
+		Tokenizer::tokenize_with_functions(raw, &[]).unwrap()
+	}
+
+	fn force(x: &str, y: &str) -> Force {
+		Force::from_scripts(&tokens(x), &tokens(y), Vec::<Token>::new(), &FunctionLibrary::default()).unwrap()
+	}
+
+	#[test]
+	fn applies_unit_less_forces() {
+		let f = force("(400 - px)/100", "-0.1vx");
+		assert_eq!(f.check_dimensions().unwrap().len(), 2);
+
+		let context = hash_map!{"px".to_string() => 300f32, "vx".to_string() => 2f32};
+		let value = f.evaluate(&context, &mut RandomStream::default()).unwrap();
+		assert_eq!((value.x, value.y), (1f32, -0.2f32));
+	}
+
+	#[test]
+	fn checks_force_dimensions() {
+		assert!(force("0.01[N/m](400 - px)", "m g").check_dimensions().unwrap().is_empty());
+		assert!(force("1", "").check_dimensions().unwrap().is_empty());
+		assert!(force("px + vx", "").check_dimensions().is_err());
+	}
+
+	/// Environment shared by points, with the triggers written in `triggers`.
+	fn environment(triggers: &[&str]) -> Rc::<RefCell::<Environment>> {
+		let environment = Environment {
+			triggers: triggers.iter().map(|t| Trigger::build(t, &FunctionLibrary::default()).unwrap()).collect(),
+			..Default::default()
+		};
+		Rc::new(RefCell::new(environment))
+	}
+
+	/// Point at the origin moving along x by `speed` every step, without forces.
+	fn moving_point(speed: f32, environment: &Rc::<RefCell::<Environment>>) -> Point {
+		let mut point = Point::new(Vector2::zero(), Rc::new(RefCell::new(HashMap::new())), Rc::clone(environment));
+		point.set_speed(Vector2::new(speed, 0f32));
+		point
+	}
+
+	#[test]
+	fn measures_empty_worlds() {
+		let sums = vec![Parser::build(&tokens("sum(px)")).unwrap()];
+		let aggregates = Aggregates::measure(&World::new(), &sums);
+
+		assert_eq!(aggregates.values["count"], 0f32);
+		assert_eq!((aggregates.values["meanx"], aggregates.values["meany"]), (0f32, 0f32));
+		assert_eq!(aggregates.values["sum(px)"], 0f32);
+		assert_eq!(aggregates.nearest_distance(Vector2::zero(), None), f32::INFINITY);
+	}
+
+	#[test]
+	fn measures_worlds() {
+		let environment = environment(&[]);
+		let forces = Rc::new(RefCell::new(HashMap::new()));
+		let mut world = [(0f32, 0f32), (4f32, 0f32), (4f32, 6f32)].into_iter()
+			.map(|(x, y)| Point::new(Vector2::new(x, y), Rc::clone(&forces), Rc::clone(&environment)))
+			.collect::<World>();
+		world[1].set_mass(2f32);
+		let sums = vec![Parser::build(&tokens("sum(px)")).unwrap(), Parser::build(&tokens("sum(m)")).unwrap()];
+		let aggregates = Aggregates::measure(&world, &sums);
+
+		assert_eq!(aggregates.values["count"], 3f32);
+		// The center of mass weighs positions by masses
+		assert_eq!((aggregates.values["meanx"], aggregates.values["meany"]), (3f32, 1.5f32));
+		assert_eq!((aggregates.values["sum(px)"], aggregates.values["sum(m)"]), (8f32, 4f32));
+		let nearest = world.iter().map(|p| aggregates.nearest_distance(p.position(), Some(p.index))).collect::<Vec::<f32>>();
+		assert_eq!(nearest, vec![4f32, 4f32, 6f32]);
+		assert_eq!(aggregates.nearest_distance(Vector2::new(4f32, 3f32), None), 3f32);
+	}
+
+	#[test]
+	fn fires_world_triggers_on_rising_edges() {
+		let environment = environment(&["when t > 2 && t < 5 || t > 6: pause", "when t >= 0: pause"]);
+		let mut fired = Vec::<u32>::new();
+		for t in 0..10 {
+			environment.borrow_mut().time = t as f32;
+			if !environment.borrow_mut().check_triggers().is_empty() {
+				fired.push(t);
+			}
+		}
+		// The second trigger is already true at its first check
+		assert_eq!(fired, vec![3, 7]);
+	}
+
+	#[test]
+	fn fires_point_triggers_once_while_true() {
+		let environment = environment(&["when px > 5: log"]);
+		let mut point = moving_point(2f32, &environment);
+		let fired = (0..10).map(|_| point.simulate().len()).collect::<Vec::<usize>>();
+		// The point passes 5 at its third step and stays beyond
+		assert_eq!(fired, vec![0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+	}
+
+	#[test]
+	fn refuses_non_positive_masses() {
+		let environment = environment(&["when px > 3: m = 0; vx = 3", "when px > 5: m = 2"]);
+		let mut point = moving_point(2f32, &environment);
+
+		// The other assignments still apply
+		point.simulate();
+		point.simulate();
+		assert_eq!((point.mass(), point.speed().x), (1f32, 3f32));
+		point.simulate();
+		assert_eq!(point.mass(), 2f32);
+		assert!(point.position().x.is_finite());
+	}
+
+	#[test]
+	fn refuses_undeclared_state_updates() {
+		let state = |initial: &str, update: &str| StateVariables {
+			initial: Program::build(&tokens(initial), "").unwrap(),
+			update: Program::build(&tokens(update), "").unwrap()
+		};
+		assert!(state("age = 0; charge = 1", "age = age + dt").check().is_ok());
+		assert!(state("age = 0", "aeg = age + dt").check().is_err());
+		assert!(state("px = 0", "").check().is_err());
+	}
+
+	#[test]
+	fn keeps_the_source_of_rewritten_forces() {
+		let source = || ForceSource {x: "typed x".to_string(), ..Default::default()};
+		assert!(force("-0.01(px - 400)", "0").written_as(source()).source.is_none());
+		assert!(force("-0.01(p - mean)", "").written_as(source()).source.is_some());
+
+		let mut functions = FunctionLibrary::default();
+		functions.define("twice", vec!["a".to_string()], Parser::build(&tokens("2a")).unwrap()).unwrap();
+		let x = Tokenizer::tokenize_with_functions("twice(px)", &functions.names()).unwrap();
+		let f = Force::from_scripts(&x, &[], Vec::<Token>::new(), &functions).unwrap().written_as(source());
+		assert_eq!(f.source.unwrap().x, "typed x");
+	}
+
+	#[test]
+	fn keeps_inlined_potentials() {
+		let mut functions = FunctionLibrary::default();
+		let body = Parser::build(&tokens("k*d^2/2")).unwrap();
+		functions.define("spring", vec!["d".to_string(), "k".to_string()], body).unwrap();
+		let potential = Tokenizer::tokenize_with_functions("spring(px - 400, 2)", &functions.names()).unwrap();
+		let f = Force::from_potential(potential, &functions).unwrap();
+
+		// Later changes to the library don't change the force
+		functions.remove("spring").unwrap();
+		let context = hash_map!{"px".to_string() => 403f32, "py".to_string() => 0f32};
+		assert_eq!(f.evaluate_potential(&context).unwrap(), Some(9f32));
+		let value = f.evaluate(&context, &mut RandomStream::default()).unwrap();
+		assert_eq!((value.x, value.y), (-6f32, 0f32));
+		assert_eq!(Force::new().evaluate_potential(&context).unwrap(), None);
+	}
+}
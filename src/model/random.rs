@@ -0,0 +1,116 @@
+//! Deterministic random numbers for expressions. A draw only depends on the world seed, the
+//! point drawing it, the step and the number of previous draws, so that runs can be replayed.
+
+use std::f32::consts::PI;
+
+/// Functions whose result isn't given by their arguments only, which can't be folded into constants.
+pub const RANDOM_FUNCTIONS: [&str; 3] = ["rand", "randn", "noise"];
+
+/// Point index no point takes, whose stream serves draws made outside of any point, such as
+/// world triggers and field previews.
+pub const WORLD_STREAM: u64 = u64::MAX;
+
+/// Mixes the bits of `x` (SplitMix64 finalizer), close values giving unrelated results.
+fn mix(x: u64) -> u64 {
+	let mut x = x.wrapping_add(0x9E3779B97F4A7C15);
+	x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+	x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+	x ^ (x >> 31)
+}
+
+/// Uniform number in [0, 1) taken from the high bits of `hash`.
+fn unit(hash: u64) -> f32 {
+	(hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Random numbers drawn by the expressions of a point during one step.
+#[derive(Debug, Clone, Default)]
+pub struct RandomStream {
+	seed: u64,
+	key: u64,
+	draws: u64
+}
+
+impl RandomStream {
+	/// Stream of the point of creation index `point` at step `step`, independent of the
+	/// streams of other points and steps.
+	pub fn new(seed: u64, point: u64, step: u64) -> Self {
+		RandomStream {
+			seed,
+			key: mix(mix(seed ^ mix(point)) ^ step),
+			draws: 0
+		}
+	}
+
+	/// Uniform number in [0, 1).
+	pub fn uniform(&mut self) -> f32 {
+		self.draws += 1;
+		unit(mix(self.key ^ mix(self.draws)))
+	}
+
+	/// Number of the standard normal distribution, by the Box-Muller transform.
+	pub fn normal(&mut self) -> f32 {
+		let (u, v) = (self.uniform(), self.uniform());
+		(-2f32 * (1f32 - u).ln()).sqrt() * (2f32 * PI * v).cos()
+	}
+
+	/// Smooth noise between -1 and 1, shared by every point of the world. It varies over
+	/// distances of about 1 in each coordinate, `noise(0.01px, 0.01py, 0.05t)` changing every
+	/// 100 pixels and 20 steps.
+	pub fn noise(&self, x: f32, y: f32, t: f32) -> f32 {
+		let lattice = |i: i64, j: i64, k: i64| 2f32 * unit(mix(self.seed ^ mix(i as u64 ^ mix(j as u64 ^ mix(k as u64))))) - 1f32;
+		let smooth = |f: f32| f * f * (3f32 - 2f32 * f);
+		let lerp = |a: f32, b: f32, f: f32| a + (b - a) * smooth(f);
+
+		let (i, j, k) = (x.floor() as i64, y.floor() as i64, t.floor() as i64);
+		let (fx, fy, ft) = (x - x.floor(), y - y.floor(), t - t.floor());
+
+		// Interpolates the values of the 8 corners of the cell around (x, y, t)
+		let plane = |k: i64| lerp(
+			lerp(lattice(i, j, k), lattice(i + 1, j, k), fx),
+			lerp(lattice(i, j + 1, k), lattice(i + 1, j + 1, k), fx),
+			fy
+		);
+		lerp(plane(k), plane(k + 1), ft)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::parsing::Parser;
+	use super::super::tokening::Tokenizer;
+	use common_macros::hash_map;
+
+	fn draws(mut random: RandomStream) -> [f32; 3] {
+		[random.uniform(), random.normal(), random.noise(0.5, 1.5, 2.5)]
+	}
+
+	#[test]
+	fn replays_the_same_draws() {
+		assert_eq!(draws(RandomStream::new(42, 3, 10)), draws(RandomStream::new(42, 3, 10)));
+		assert_ne!(draws(RandomStream::new(42, 3, 10)), draws(RandomStream::new(43, 3, 10)));
+		assert_ne!(draws(RandomStream::new(42, 3, 10)), draws(RandomStream::new(42, 4, 10)));
+		assert_ne!(draws(RandomStream::new(42, 3, 10)), draws(RandomStream::new(42, 3, 11)));
+	}
+
+	#[test]
+	fn replays_the_same_expression() {
+		let expression = Parser::build(&Tokenizer::tokenize_with_functions("rand() + randn() + noise(px, py, t)", &[]).unwrap()).unwrap();
+		let context = hash_map!{"px".to_string() => 1.5f32, "py".to_string() => 2f32, "t".to_string() => 10f32};
+		let evaluate = |point: u64| expression.evaluate_with(&context, &mut RandomStream::new(7, point, 10)).unwrap();
+
+		assert_eq!(evaluate(0), evaluate(0));
+		assert_ne!(evaluate(0), evaluate(1));
+	}
+
+	#[test]
+	fn draws_in_range() {
+		let mut random = RandomStream::new(1, 2, 3);
+		for i in 0..1000 {
+			assert!((0f32..1f32).contains(&random.uniform()));
+			let noise = random.noise(i as f32 * 0.37, i as f32 * 0.11, i as f32 * 0.05);
+			assert!((-1f32..=1f32).contains(&noise));
+		}
+	}
+}
@@ -1,28 +1,88 @@
-#[derive(Debug, PartialEq)]
+use super::units::Dimension;
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
 	Value (f32),
 	Variable (String),
+	Function (String), // Always followed by the opening parenthesis of its arguments
 	Parenthesis (bool), // true: Open, false: Closed
-	Operator(char)
+	Operator(char),
+	Comma,
+	Unit (Dimension), // Always follows the value it applies to
+	Assignment, // Single '=' of a script statement
+	Semicolon // Separates script statements
+}
+
+/// Functions available in expressions, with their number of arguments.
+/// A function name is only read as a call when immediately followed by a parenthesis,
+/// `sin (t)` being the product of a `sin` variable and `t`.
+/// `if(condition, a, b)` only evaluates the selected branch.
+/// `vec`, `norm`, `dot` and `normalize` work on vectors, expanded before evaluation.
+/// `rand()` is uniform in [0, 1), `randn()` normal and `noise(x, y, t)` smooth noise in [-1, 1].
+/// `sum(e)` is the sum of `e` over every point of the world, measured once per step.
+pub const FUNCTIONS: [(&str, usize); 19] = [
+	("sin", 1), ("cos", 1), ("tan", 1), ("sqrt", 1), ("abs", 1), ("exp", 1), ("ln", 1),
+	("min", 2), ("max", 2), ("atan2", 2), ("if", 3),
+	("vec", 2), ("norm", 1), ("dot", 2), ("normalize", 1),
+	("rand", 0), ("randn", 0), ("noise", 3),
+	("sum", 1)
+];
+
+/// Operators written with two characters, stored as a single one in `Token::Operator`.
+pub const DOUBLE_OPERATORS: [(&str, char); 6] = [
+	(">=", '≥'), ("<=", '≤'), ("==", '='), ("!=", '≠'), ("&&", '&'), ("||", '|')
+];
+
+/// How an operator is written in expressions.
+pub fn operator_text(op: char) -> String {
+	match DOUBLE_OPERATORS.iter().find(|(_, c)| *c == op) {
+		Some((text, _)) => text.to_string(),
+		None => op.to_string()
+	}
 }
 
 #[derive(Debug)]
 pub enum TokenizerError {
 	UnmatchedParenthesis(usize),
-	UnexpectedParenthesis(usize),
 	UnexpectedDot(usize),
 	UnexpectedOperator(usize),
+	UnexpectedComma(usize),
+	UnexpectedSemicolon(usize),
 	TooManyDots(usize),
 	InvalidCharacter(usize),
+	InvalidUnit(usize),
 	InternalError(usize, String)
 }
 
+/// Positions are those of the character where tokenizing stopped.
+impl fmt::Display for TokenizerError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			TokenizerError::UnmatchedParenthesis(i) => write!(f, "unmatched parenthesis at {i}"),
+			TokenizerError::UnexpectedDot(i) => write!(f, "unexpected dot at {i}"),
+			TokenizerError::UnexpectedOperator(i) => write!(f, "unexpected operator at {i}"),
+			TokenizerError::UnexpectedComma(i) => write!(f, "unexpected comma at {i}"),
+			TokenizerError::UnexpectedSemicolon(i) => write!(f, "unexpected semicolon at {i}"),
+			TokenizerError::TooManyDots(i) => write!(f, "too many dots in the number at {i}"),
+			TokenizerError::InvalidCharacter(i) => write!(f, "invalid character at {i}"),
+			TokenizerError::InvalidUnit(i) => write!(f, "invalid unit at {i}"),
+			TokenizerError::InternalError(i, message) => write!(f, "internal error at {i} : {message}")
+		}
+	}
+}
+
 #[derive(Debug)]
 pub enum TokenizerState {
 	Initial,
 	NumberBeforeDot,
 	NumberAfterDot,
+	Exponent,
 	VariableName,
+	Unit,
+	OperatorStart (char), // First character of an operator which may be written with two
+
 	Error (TokenizerError)
 }
 
@@ -43,18 +103,36 @@ impl Tokenizer {
 		}
 	}
 
-	/// Tokenizes a raw &str and checks expression syntax
-	pub fn tokenize(raw: &str) -> Result::<Vec::<Token>, TokenizerError> {
+	/// Tokenizes a raw &str and checks expression syntax.
+	/// Operands written side by side are multiplied: `2px`, `3(px + 1)`, `(px)(py)`, `2 sin(t)` or `vx vy`.
+	/// Numbers accept exponents, `6.67e-11`, and may be followed by a unit in brackets, `9.81[m/s^2]`.
+	/// Built-in functions and the names of `functions` followed by a parenthesis are read as calls.
+	pub fn tokenize_with_functions(raw: &str, functions: &[&str]) -> Result::<Vec::<Token>, TokenizerError> {
 		let mut r = Vec::new();                  // Result vector
 		let mut parenthesis_stack = 0u32;        // Checks if every opened parenthesis is closed
 		let mut state = TokenizerState::Initial; // This function is a state machine
 		let mut buffer = String::new();          // Contains a substring of raw, used for parsing
 
-		for (i, c) in raw.chars().enumerate() {
+		let chars = raw.chars().collect::<Vec::<char>>();
+		// An 'e' after a number only starts an exponent when digits follow, `2e` being 2 times e
+		let starts_exponent = |i: usize| matches!(
+			(chars.get(i + 1), chars.get(i + 2)),
+			(Some('0' ..= '9'), _) | (Some('+' | '-'), Some('0' ..= '9'))
+		);
+
+		for (i, &c) in chars.iter().enumerate() {
 			
 			// Behaviours common to every state
 			let mut should_push_parenthesis = false;
 			if c == ' ' {
+				// Spaces end numbers and names
+				if let TokenizerState::NumberBeforeDot | TokenizerState::NumberAfterDot | TokenizerState::Exponent | TokenizerState::VariableName = state {
+					r.push(Self::parse_buffer(&mut buffer).unwrap());
+					state = TokenizerState::Initial;
+				}
+				else if let TokenizerState::OperatorStart(first) = state {
+					state = Self::push_single_operator(first, i - 1, &mut r);
+				}
 				continue;
 			}
 			else if c == ')' {
@@ -74,106 +152,107 @@ impl Tokenizer {
 			// The state machine
 			match state {
 				TokenizerState::Initial => {
+					state = Self::initial_transition(c, i, &mut r, &mut buffer, &mut parenthesis_stack);
+				},
+				TokenizerState::NumberBeforeDot => {
 					state = match c {
 						'0' ..= '9' => {
 							buffer.push(c);
 							TokenizerState::NumberBeforeDot
 						},
-						'a' ..= 'z' | 'A' ..= 'Z' => {
+						'.' => {
 							buffer.push(c);
-							TokenizerState::VariableName
-						}
-						'+' | '-' | '*' | '/' | '>' | '<' => {
-							// Two operators in a row throws UnexpectedOperator.
-							match r.last() {
-								Some(Token::Operator(_)) => TokenizerState::Error(TokenizerError::UnexpectedOperator(i)),
-								_ => {
-									r.push(Token::Operator(c));
-									TokenizerState::Initial
-								}
-							}
+							TokenizerState::NumberAfterDot
 						},
-						')' => TokenizerState::Initial, // The rest of this behaviour is already implemented in common behaviours at start of loop.
-						'(' => {
-							r.push(Token::Parenthesis(true));
-							parenthesis_stack += 1u32;
-							TokenizerState::Initial
+						'e' | 'E' if starts_exponent(i) => {
+							buffer.push(c);
+							TokenizerState::Exponent
 						},
-						'.' => TokenizerState::Error(TokenizerError::UnexpectedDot(i)),
-						 _  => TokenizerState::Error(TokenizerError::InvalidCharacter(i)) 
+						// Any other character starts a new token
+						 _  => {
+							r.push(Self::parse_buffer(&mut buffer).unwrap());
+							Self::initial_transition(c, i, &mut r, &mut buffer, &mut parenthesis_stack)
+						}
 					}
 				},
-				TokenizerState::NumberBeforeDot => {
+				TokenizerState::NumberAfterDot => {
 					state = match c {
 						'0' ..= '9' => {
 							buffer.push(c);
-							TokenizerState::NumberBeforeDot
-						}
-						'+' | '-' | '*' | '/' | '>' | '<' => {
-							r.push(Self::parse_buffer(&mut buffer).unwrap());
-							r.push(Token::Operator(c));
-							TokenizerState::Initial
-						},
-						')' => {									
-							r.push(Self::parse_buffer(&mut buffer).unwrap());
-							TokenizerState::Initial								
-						},
-						'(' => {
-							// TODO: Allow multiplication inference (ex: 3(1 + 2) instead of 3 * (1 + 2))
-							// (If implemented here, multiplication inference should also be implemented
-							//  in NumberAfterDot)
-							TokenizerState::Error(TokenizerError::UnexpectedParenthesis(i))
+							TokenizerState::NumberAfterDot
 						},
-						'.' => {
+						'.' => TokenizerState::Error(TokenizerError::TooManyDots(i)),
+						'e' | 'E' if starts_exponent(i) => {
 							buffer.push(c);
-							TokenizerState::NumberAfterDot
+							TokenizerState::Exponent
 						},
-						 _  => TokenizerState::Error(TokenizerError::InvalidCharacter(i)) 
+						 _  => {
+							r.push(Self::parse_buffer(&mut buffer).unwrap());
+							Self::initial_transition(c, i, &mut r, &mut buffer, &mut parenthesis_stack)
+						}
 					}
 				},
-				TokenizerState::NumberAfterDot => {
+				TokenizerState::Exponent => {
 					state = match c {
 						'0' ..= '9' => {
 							buffer.push(c);
-							TokenizerState::NumberAfterDot
-						}
-						'+' | '-' | '*' | '/' | '>' | '<' => {
-							r.push(Self::parse_buffer(&mut buffer).unwrap());
-							r.push(Token::Operator(c));
-							TokenizerState::Initial
+							TokenizerState::Exponent
 						},
-						')' => {	
+						'+' | '-' if buffer.ends_with(['e', 'E']) => {
+							buffer.push(c);
+							TokenizerState::Exponent
+						},
+						'.' => TokenizerState::Error(TokenizerError::UnexpectedDot(i)),
+						 _  => {
 							r.push(Self::parse_buffer(&mut buffer).unwrap());
-							TokenizerState::Initial
+							Self::initial_transition(c, i, &mut r, &mut buffer, &mut parenthesis_stack)
+						}
+					}
+				},
+				TokenizerState::Unit => {
+					state = match c {
+						']' => match Dimension::parse(&buffer) {
+							Some(d) => {
+								r.push(Token::Unit(d));
+								buffer.clear();
+								TokenizerState::Initial
+							},
+							None => TokenizerState::Error(TokenizerError::InvalidUnit(i))
 						},
-						'(' => {
-							// TODO: [See NumberBeforeDot arm of current match statement comment...]
-							TokenizerState::Error(TokenizerError::UnexpectedParenthesis(i))
+						'0' ..= '9' | 'a' ..= 'z' | 'A' ..= 'Z' | '*' | '/' | '^' | '-' => {
+							buffer.push(c);
+							TokenizerState::Unit
 						},
-						'.' => TokenizerState::Error(TokenizerError::TooManyDots(i)),
-						 _  => TokenizerState::Error(TokenizerError::InvalidCharacter(i)) 
+						 _  => TokenizerState::Error(TokenizerError::InvalidUnit(i))
 					}
 				},
 				TokenizerState::VariableName => {
 					state = match c {
-						'0' ..= '9' | 'a' ..= 'z' | 'A' ..= 'Z' => {
+						// Names start with a letter, and may then contain digits and underscores
+						'0' ..= '9' | 'a' ..= 'z' | 'A' ..= 'Z' | '_' => {
 							buffer.push(c);
 							TokenizerState::VariableName
-						}
-						'+' | '-' | '*' | '/' | '>' | '<' => {
-							r.push(Self::parse_buffer(&mut buffer).unwrap());
-							r.push(Token::Operator(c));
-							TokenizerState::Initial
 						},
-						')' => {
-							r.push(Self::parse_buffer(&mut buffer).unwrap());
+						'(' if FUNCTIONS.iter().any(|(name, _)| *name == buffer) || functions.contains(&buffer.as_str()) => {
+							r.push(Token::Function(buffer.clone()));
+							buffer.clear();
+							r.push(Token::Parenthesis(true));
+							parenthesis_stack += 1u32;
 							TokenizerState::Initial
 						},
-						'(' => {
-							TokenizerState::Error(TokenizerError::UnexpectedParenthesis(i))
-						},
-						'.' => TokenizerState::Error(TokenizerError::UnexpectedDot(i)),
-						 _  => TokenizerState::Error(TokenizerError::InvalidCharacter(i)) 
+						 _  => {
+							r.push(Self::parse_buffer(&mut buffer).unwrap());
+							Self::initial_transition(c, i, &mut r, &mut buffer, &mut parenthesis_stack)
+						}
+					}
+				},
+				TokenizerState::OperatorStart(first) => {
+					state = match DOUBLE_OPERATORS.iter().find(|(text, _)| text.starts_with(first) && text.ends_with(c)) {
+						Some((_, op)) => Self::push_operator(*op, i - 1, &mut r),
+						None => match Self::push_single_operator(first, i - 1, &mut r) {
+							TokenizerState::Initial => Self::initial_transition(c, i, &mut r, &mut buffer, &mut parenthesis_stack),
+							error => error
+						}
 					}
 				},
 				TokenizerState::Error(e) => {
@@ -191,6 +270,14 @@ impl Tokenizer {
 		if let TokenizerState::Error(e) = state {
 			return Err(e);
 		}
+		if let TokenizerState::Unit = state {
+			return Err(TokenizerError::InvalidUnit(chars.len()));
+		}
+		if let TokenizerState::OperatorStart(first) = state {
+			if let TokenizerState::Error(e) = Self::push_single_operator(first, chars.len() - 1, &mut r) {
+				return Err(e);
+			}
+		}
 
 		// If expression ends by a variable name, it is not parsed until now.
 		if let Some(token) = Self::parse_buffer(&mut buffer) {
@@ -205,19 +292,134 @@ impl Tokenizer {
 		}
 	}
 
-	pub fn untokenize(tokens: &Vec::<Token>) -> String {
-		
-		let mut result = String::new();
-		for token in tokens {
-			match token {
-				Token::Value (v) => result.push_str(&v.to_string()),
-				Token::Variable (v) => result.push_str(&v),
-				Token::Parenthesis(true) => result.push('('),
-				Token::Parenthesis(false) => result.push(')'),
-				Token::Operator(o) => result.push(*o)
+	/// Reads `c` outside of any number or name, which is also how the other states handle a
+	/// character starting a new token. An implicit multiplication is inserted before an operand
+	/// directly following another one.
+	fn initial_transition(c: char, i: usize, r: &mut Vec::<Token>, buffer: &mut String, parenthesis_stack: &mut u32) -> TokenizerState {
+		let follows_operand = matches!(r.last(), Some(Token::Value(_) | Token::Variable(_) | Token::Parenthesis(false) | Token::Unit(_)));
+		if follows_operand && matches!(c, '0' ..= '9' | 'a' ..= 'z' | 'A' ..= 'Z' | '(') {
+			r.push(Token::Operator('*'));
+		}
+
+		match c {
+			'0' ..= '9' => {
+				buffer.push(c);
+				TokenizerState::NumberBeforeDot
+			},
+			'a' ..= 'z' | 'A' ..= 'Z' => {
+				buffer.push(c);
+				TokenizerState::VariableName
+			}
+			'+' | '-' | '*' | '/' | '^' => Self::push_operator(c, i, r),
+			// Might be the start of a two characters operator, decided on the next character
+			'>' | '<' | '=' | '!' | '&' | '|' => TokenizerState::OperatorStart(c),
+			';' => {
+				// Statements can't be cut by parentheses
+				if *parenthesis_stack != 0 {
+					TokenizerState::Error(TokenizerError::UnexpectedSemicolon(i))
+				} else {
+					r.push(Token::Semicolon);
+					TokenizerState::Initial
+				}
+			},
+			',' => {
+				// Commas separate non empty arguments
+				if *parenthesis_stack == 0 || !follows_operand {
+					TokenizerState::Error(TokenizerError::UnexpectedComma(i))
+				} else {
+					r.push(Token::Comma);
+					TokenizerState::Initial
+				}
+			},
+			')' => TokenizerState::Initial, // The rest of this behaviour is already implemented in common behaviours at start of loop.
+			'(' => {
+				r.push(Token::Parenthesis(true));
+				*parenthesis_stack += 1u32;
+				TokenizerState::Initial
+			},
+			'[' => {
+				// Units only apply to numbers
+				match r.last() {
+					Some(Token::Value(_)) => TokenizerState::Unit,
+					_ => TokenizerState::Error(TokenizerError::InvalidUnit(i))
+				}
+			},
+			'.' => TokenizerState::Error(TokenizerError::UnexpectedDot(i)),
+			 _  => TokenizerState::Error(TokenizerError::InvalidCharacter(i)) 
+		}
+	}
+
+	/// Pushes the operator `op` read at `i`. Two operators in a row throws UnexpectedOperator,
+	/// except for the unary `!` which may follow any operator but no operand.
+	fn push_operator(op: char, i: usize, r: &mut Vec::<Token>) -> TokenizerState {
+		match (op, r.last()) {
+			('!', Some(Token::Value(_) | Token::Variable(_) | Token::Parenthesis(false) | Token::Unit(_))) => TokenizerState::Error(TokenizerError::UnexpectedOperator(i)),
+			('!', _) => {
+				r.push(Token::Operator(op));
+				TokenizerState::Initial
+			},
+			(_, Some(Token::Operator(_))) => TokenizerState::Error(TokenizerError::UnexpectedOperator(i)),
+			_ => {
+				r.push(Token::Operator(op));
+				TokenizerState::Initial
 			}
 		}
+	}
 
-		result
+	/// Pushes the first character of a possibly two characters operator when it isn't followed by its second one.
+	/// A single `=` assigns the variable before it.
+	fn push_single_operator(op: char, i: usize, r: &mut Vec::<Token>) -> TokenizerState {
+		match (op, r.last()) {
+			('=', Some(Token::Variable(_))) => {
+				r.push(Token::Assignment);
+				TokenizerState::Initial
+			},
+			// Only valid when doubled
+			('=' | '&' | '|', _) => TokenizerState::Error(TokenizerError::InvalidCharacter(i)),
+			_ => Self::push_operator(op, i, r)
+		}
 	}
  }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tokens(raw: &str) -> Vec::<Token> {
+		Tokenizer::tokenize_with_functions(raw, &[]).unwrap()
+	}
+
+	#[test]
+	fn multiplies_operands_side_by_side() {
+		assert_eq!(tokens("2px"), tokens("2*px"));
+		assert_eq!(tokens("3(px+1)"), tokens("3*(px+1)"));
+		assert_eq!(tokens("(px)(py)"), tokens("(px)*(py)"));
+		assert_eq!(tokens("2 sin(t)"), tokens("2*sin(t)"));
+		assert_eq!(tokens("vx vy"), tokens("vx*vy"));
+	}
+
+	#[test]
+	fn reads_calls_of_known_functions() {
+		assert_eq!(tokens("sin(t)")[0], Token::Function("sin".to_string()));
+		assert_eq!(tokens("sin (t)"), tokens("sin*(t)"));
+		assert_eq!(Tokenizer::tokenize_with_functions("spring(px)", &["spring"]).unwrap()[0], Token::Function("spring".to_string()));
+		assert_eq!(tokens("spring(px)"), tokens("spring*(px)"));
+	}
+
+	#[test]
+	fn reads_exponents() {
+		assert_eq!(tokens("6.67e-11"), vec![Token::Value(6.67e-11)]);
+		assert_eq!(tokens("1.5e+2"), vec![Token::Value(150f32)]);
+		assert_eq!(tokens("2e3"), vec![Token::Value(2000f32)]);
+		// Without digits after it, `e` is the constant
+		assert_eq!(tokens("2e"), tokens("2*e"));
+		assert_eq!(tokens("2e-px"), tokens("2*e-px"));
+	}
+
+	#[test]
+	fn reads_units_after_numbers() {
+		assert_eq!(tokens("9.81[m/s^2]"), vec![Token::Value(9.81), Token::Unit(Dimension::LENGTH / Dimension::TIME.pow(2))]);
+		assert_eq!(tokens("2[N]"), vec![Token::Value(2f32), Token::Unit(Dimension::FORCE)]);
+		assert!(matches!(Tokenizer::tokenize_with_functions("2[furlong]", &[]), Err(TokenizerError::InvalidUnit(_))));
+	}
+}
@@ -0,0 +1,165 @@
+//! Event triggers written `when condition: actions`, e.g. `when py > 400: vy = -0.8vy`. A
+//! trigger fires when its condition becomes true, rather than at every step it is true, so that
+//! a point crossing the ground bounces once whatever the number of steps it stays below.
+
+use raylib::prelude::Vector2;
+
+use super::parsing::{Expression, FunctionLibrary, Parser, Program, VariableContext};
+use super::physics::Point;
+use super::random::RandomStream;
+use super::tokening::Tokenizer;
+use super::vectors::{expand_program, expand_scalar};
+
+/// Variables of a point a trigger may assign, along with the state variables.
+pub const ASSIGNABLE: [&str; 5] = ["px", "py", "vx", "vy", "m"];
+
+/// Effect of a fired trigger on the application.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+	Pause,
+	/// Text of the trigger to print
+	Log (String),
+	/// Position and speed of the point to create
+	Spawn (Vector2, Vector2)
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+	Pause,
+	Log,
+	/// Position and speed of the point to create, the speed being 0 when omitted
+	Spawn (Expression, Expression, Expression, Expression)
+}
+
+#[derive(Debug, Clone)]
+pub struct Trigger {
+	/// Text the trigger was written as, identifying it
+	pub source: String,
+	condition: Expression,
+	/// Statements run when the trigger fires, the names they bind among `ASSIGNABLE` and the
+	/// state variables being set on the point, and the other ones being local to the trigger
+	assignments: Program,
+	actions: Vec::<Action>
+}
+
+impl Trigger {
+	/// Builds the trigger written in `text`, whose actions are `;` separated statements among
+	/// assignments, `pause`, `log` and `spawn(x, y)` or `spawn(x, y, vx, vy)`.
+	pub fn build(text: &str, functions: &FunctionLibrary) -> Result<Self, String> {
+		let Some((condition, actions)) = text.trim().strip_prefix("when ").and_then(|t| t.split_once(':')) else {
+			return Err("expected \"when condition: actions\"".to_string());
+		};
+		let mut names = functions.names();
+		names.push("spawn");
+		let tokenize = |s: &str| Tokenizer::tokenize_with_functions(s, &names).map_err(|e| format!("{e}"));
+		let scalar = |e: &Expression| functions.inline(e)
+			.map_err(|e| format!("{e}"))
+			.and_then(|e| expand_scalar(&e).map_err(|e| e.to_string()));
+
+		let condition = scalar(&Parser::build(&tokenize(condition)?).map_err(|e| format!("{e}"))?)?;
+		condition.dimension().map_err(|e| e.to_string())?;
+
+		let mut assignments = Program::default();
+		let mut built = Vec::<Action>::new();
+		for (name, expression) in Program::build(&tokenize(actions)?, "").map_err(|e| format!("{e}"))?.bindings {
+			match (name.as_str(), &expression) {
+				("", Expression::Variable(action)) if action == "pause" => built.push(Action::Pause),
+				("", Expression::Variable(action)) if action == "log" => built.push(Action::Log),
+				("", Expression::Call(action, args)) if action == "spawn" => {
+					let args = args.iter().map(scalar).collect::<Result<Vec::<Expression>, String>>()?;
+					built.push(match args.as_slice() {
+						[x, y] => Action::Spawn(x.clone(), y.clone(), Expression::Value(0f32), Expression::Value(0f32)),
+						[x, y, vx, vy] => Action::Spawn(x.clone(), y.clone(), vx.clone(), vy.clone()),
+						_ => return Err(format!("spawn expects 2 or 4 arguments, got {}", args.len()))
+					});
+				},
+				("", _) => return Err(format!("unknown action {expression}")),
+				(name, _) => assignments.bindings.push((name.to_string(), expression))
+			}
+		}
+
+		let assignments = functions.inline_program(&assignments)
+			.map_err(|e| format!("{e}"))
+			.and_then(|p| expand_program(&p, &[]).map_err(|e| e.to_string()))?;
+		assignments.check_dimensions().map_err(|e| e.to_string())?;
+		if let Some((name, _)) = assignments.bindings.iter().find(|(name, _)| Point::is_reserved(name) && !ASSIGNABLE.contains(&name.as_str())) {
+			return Err(format!("\"{name}\" can't be assigned"));
+		}
+
+		Ok(Trigger {
+			source: text.trim().to_string(),
+			condition,
+			assignments,
+			actions: built
+		})
+	}
+
+	/// Whether the trigger is checked once per step on the world rather than on every point,
+	/// as it neither reads nor assigns a variable of the points, `state` being the state variables.
+	pub fn is_global(&self, state: &[&str]) -> bool {
+		let reads_point = |e: &Expression| uses_point(e, state);
+		self.assignments.bindings.is_empty() && !reads_point(&self.condition) && self.actions.iter().all(|a| match a {
+			Action::Spawn(x, y, vx, vy) => ![x, y, vx, vy].into_iter().any(reads_point),
+			Action::Pause | Action::Log => true
+		})
+	}
+
+	/// Calls to `sum` in the trigger, to be measured before checking it.
+	pub fn sums(&self) -> Vec::<Expression> {
+		let mut r = self.condition.calls("sum");
+		for (_, e) in self.assignments.bindings.iter() {
+			r.append(&mut e.calls("sum"));
+		}
+		for action in self.actions.iter() {
+			if let Action::Spawn(x, y, vx, vy) = action {
+				r.extend([x, y, vx, vy].into_iter().flat_map(|e| e.calls("sum")));
+			}
+		}
+		r
+	}
+
+	/// Whether the condition holds in `context`.
+	pub fn condition(&self, context: &VariableContext, random: &mut RandomStream) -> Result<bool, String> {
+		self.condition.evaluate_with(context, random)
+			.map(|v| v != 0f32)
+			.map_err(|e| format!("{e}"))
+	}
+
+	/// Runs the actions in `context`, returning the values bound by the assignments and the
+	/// events of the other actions, which see the assigned values.
+	pub fn run(&self, context: &VariableContext, random: &mut RandomStream) -> Result<(VariableContext, Vec::<Event>), String> {
+		let values = self.assignments.evaluate_with(context, random).map_err(|e| format!("{e}"))?;
+		let mut evaluate = |e: &Expression| e.evaluate_with(&values, random).map_err(|e| format!("{e}"));
+
+		let mut events = Vec::<Event>::new();
+		for action in self.actions.iter() {
+			events.push(match action {
+				Action::Pause => Event::Pause,
+				Action::Log => Event::Log(self.source.clone()),
+				Action::Spawn(x, y, vx, vy) => Event::Spawn(
+					Vector2::new(evaluate(x)?, evaluate(y)?),
+					Vector2::new(evaluate(vx)?, evaluate(vy)?)
+				)
+			});
+		}
+
+		let assigned = self.assignments.bindings.iter()
+			.map(|(name, _)| (name.clone(), values[name]))
+			.collect::<VariableContext>();
+		Ok((assigned, events))
+	}
+}
+
+/// Whether `expression` reads a variable of the point it is evaluated on. The arguments of `sum`
+/// are summed over the world, and don't depend on the point.
+fn uses_point(expression: &Expression, state: &[&str]) -> bool {
+	match expression {
+		Expression::Value(_) | Expression::Quantity(..) => false,
+		Expression::Variable(name) => Point::VARIABLES.contains(&name.as_str()) || name == "nearest_dist" || state.contains(&name.as_str()),
+		Expression::Negation(e) | Expression::Not(e) => uses_point(e, state),
+		Expression::Operation(_, left, right) => uses_point(left, state) || uses_point(right, state),
+		Expression::Call(name, _) if name == "sum" => false,
+		Expression::Call(_, args) => args.iter().any(|a| uses_point(a, state))
+	}
+}
+
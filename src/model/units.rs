@@ -0,0 +1,223 @@
+//! Physical dimensions of quantities, used to check that expressions are consistent.
+//! The simulation measures lengths in pixels and times in steps: the `m` and `s` units stand
+//! for them, units only being used to check dimensions, never to convert values.
+
+use std::fmt;
+use std::ops::{Mul, Div};
+
+/// Exponents of the base dimensions of a quantity, e.g. length 1 and time -2 for an acceleration.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Dimension {
+	pub length: i8,
+	pub mass: i8,
+	pub time: i8
+}
+
+#[derive(Debug)]
+pub enum DimensionError {
+	/// Added, subtracted or compared quantities of different dimensions, in the written operation.
+	Mismatch (String, Dimension, Dimension),
+	/// The expression has another dimension than the one its result is used as.
+	Unexpected (Dimension, Dimension), // Expected, found
+	/// A function only accepting plain numbers got a quantity of this dimension.
+	NotDimensionless (String, Dimension),
+	/// Square root of a dimension with odd exponents.
+	OddRoot (Dimension),
+	/// Raised a quantity of this dimension to an exponent which isn't a known integer.
+	NonIntegerPower (String, Dimension)
+}
+
+impl Dimension {
+	pub const NONE: Dimension = Dimension {length: 0, mass: 0, time: 0};
+	pub const LENGTH: Dimension = Dimension {length: 1, mass: 0, time: 0};
+	pub const MASS: Dimension = Dimension {length: 0, mass: 1, time: 0};
+	pub const TIME: Dimension = Dimension {length: 0, mass: 0, time: 1};
+	pub const FORCE: Dimension = Dimension {length: 1, mass: 1, time: -2};
+
+	/// Reads units such as `m/s^2`, `kg*m/s^2` or `N`. Each `/` divides by the unit following it.
+	pub fn parse(unit: &str) -> Option<Dimension> {
+		let unit = unit.replace(' ', "");
+		if unit.is_empty() {
+			return None;
+		}
+
+		let mut r = Dimension::NONE;
+		let mut divide = false;
+		let mut rest = unit.as_str();
+		loop {
+			let end = rest.find(['*', '/']).unwrap_or(rest.len());
+			let (factor, exponent) = match rest[..end].split_once('^') {
+				Some((f, e)) => (f, e.parse::<i8>().ok()?),
+				None => (&rest[..end], 1i8)
+			};
+			let d = Self::base_unit(factor)?.pow(exponent);
+			r = if divide {r / d} else {r * d};
+
+			match rest[end..].chars().next() {
+				Some(c) => {
+					divide = c == '/';
+					rest = &rest[end + 1..];
+				},
+				None => return Some(r)
+			}
+		}
+	}
+
+	fn base_unit(name: &str) -> Option<Dimension> {
+		match name {
+			"1" => Some(Dimension::NONE),
+			"m" => Some(Dimension::LENGTH),
+			"kg" => Some(Dimension::MASS),
+			"s" => Some(Dimension::TIME),
+			"N" => Some(Dimension::FORCE),
+			"J" => Some(Dimension {length: 2, mass: 1, time: -2}),
+			_ => None
+		}
+	}
+
+	pub fn pow(self, n: i8) -> Dimension {
+		Dimension {
+			length: self.length * n,
+			mass: self.mass * n,
+			time: self.time * n
+		}
+	}
+
+	pub fn sqrt(self) -> Result<Dimension, DimensionError> {
+		if self.length % 2 != 0 || self.mass % 2 != 0 || self.time % 2 != 0 {
+			return Err(DimensionError::OddRoot(self));
+		}
+		Ok(Dimension {
+			length: self.length / 2,
+			mass: self.mass / 2,
+			time: self.time / 2
+		})
+	}
+}
+
+impl Mul for Dimension {
+	type Output = Dimension;
+
+	fn mul(self, other: Dimension) -> Dimension {
+		Dimension {
+			length: self.length + other.length,
+			mass: self.mass + other.mass,
+			time: self.time + other.time
+		}
+	}
+}
+
+impl Div for Dimension {
+	type Output = Dimension;
+
+	fn div(self, other: Dimension) -> Dimension {
+		self * other.pow(-1)
+	}
+}
+
+/// Writes the dimension as a unit, e.g. `kg*m/s^2`, parsing back with `Dimension::parse`.
+impl fmt::Display for Dimension {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let units = [("kg", self.mass), ("m", self.length), ("s", self.time)];
+
+		let mut numerator = 0usize;
+		for (name, exponent) in units.iter().filter(|(_, e)| *e > 0) {
+			if numerator > 0 {
+				write!(f, "*")?;
+			}
+			match exponent {
+				1 => write!(f, "{name}")?,
+				e => write!(f, "{name}^{e}")?
+			}
+			numerator += 1;
+		}
+		if numerator == 0 {
+			write!(f, "1")?;
+		}
+
+		for (name, exponent) in units.iter().filter(|(_, e)| *e < 0) {
+			match -exponent {
+				1 => write!(f, "/{name}")?,
+				e => write!(f, "/{name}^{e}")?
+			}
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Display for DimensionError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			DimensionError::Mismatch(operation, a, b) => write!(f, "{operation} mixes {a} and {b}"),
+			DimensionError::Unexpected(expected, found) => write!(f, "expected {expected}, found {found}"),
+			DimensionError::NotDimensionless(name, d) => write!(f, "{name} expects a plain number, got {d}"),
+			DimensionError::OddRoot(d) => write!(f, "square root of {d}"),
+			DimensionError::NonIntegerPower(operation, d) => write!(f, "{operation} raises {d} to an exponent which isn't a known integer")
+		}
+	}
+}
+
+/// Dimension of the variables and constants available in expressions, `None` for unknown ones.
+pub fn variable_dimension(name: &str) -> Option<Dimension> {
+	match name {
+		"px" | "py" | "meanx" | "meany" | "nearest_dist" => Some(Dimension::LENGTH),
+		"vx" | "vy" => Some(Dimension::LENGTH / Dimension::TIME),
+		"ax" | "ay" | "g" => Some(Dimension::LENGTH / Dimension::TIME.pow(2)),
+		"m" => Some(Dimension::MASS),
+		"t" | "dt" => Some(Dimension::TIME),
+		"pi" | "e" | "count" => Some(Dimension::NONE),
+		_ => None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::parsing::{Expression, Parser, Program};
+	use super::super::tokening::Tokenizer;
+
+	fn parse(raw: &str) -> Expression {
+		Parser::build(&Tokenizer::tokenize_with_functions(raw, &[]).unwrap()).unwrap()
+	}
+
+	fn force(raw: &str) -> Result<(), DimensionError> {
+		Program::build(&Tokenizer::tokenize_with_functions(raw, &[]).unwrap(), "x").unwrap().check_output("x", Dimension::FORCE)
+	}
+
+	#[test]
+	fn refuses_mixed_dimensions() {
+		assert!(matches!(parse("px + vx").dimension(), Err(DimensionError::Mismatch(_, a, b)) if a == Dimension::LENGTH && b == Dimension::LENGTH / Dimension::TIME));
+		assert!(matches!(parse("2px + vx").dimension(), Err(DimensionError::Mismatch(..))));
+		assert!(matches!(parse("px < t").dimension(), Err(DimensionError::Mismatch(..))));
+		assert!(matches!(parse("sin(px)").dimension(), Err(DimensionError::NotDimensionless(..))));
+		assert!(matches!(parse("sqrt(px)").dimension(), Err(DimensionError::OddRoot(_))));
+		assert!(matches!(parse("px^t").dimension(), Err(DimensionError::NotDimensionless(..))));
+		assert!(matches!(parse("px^n").dimension(), Err(DimensionError::NonIntegerPower(..))));
+	}
+
+	#[test]
+	fn gives_numbers_the_dimension_of_what_they_are_added_to() {
+		assert_eq!(parse("px - 400").dimension().unwrap(), Some(Dimension::LENGTH));
+		assert_eq!(parse("if(py > 400, vy, 0)").dimension().unwrap(), Some(Dimension::LENGTH / Dimension::TIME));
+		assert_eq!(parse("2px").dimension().unwrap(), Some(Dimension::LENGTH));
+		assert_eq!(parse("sqrt(px^2 + py^2)").dimension().unwrap(), Some(Dimension::LENGTH));
+		assert_eq!(parse("9.81[m/s^2] m").dimension().unwrap(), Some(Dimension::FORCE));
+	}
+
+	#[test]
+	fn checks_force_outputs() {
+		assert!(force("0.01[N/m](400 - px)").is_ok());
+		assert!(force("m g").is_ok());
+		assert!(force("1").is_ok());
+		assert!(force("k = 0.01[N/m]; x = k(400 - px)").is_ok());
+		assert!(matches!(force("0.01(400 - px)"), Err(DimensionError::Unexpected(expected, found)) if expected == Dimension::FORCE && found == Dimension::LENGTH));
+		assert!(matches!(force("x = 2[N]; x = vx"), Err(DimensionError::Unexpected(..))));
+	}
+
+	#[test]
+	fn prints_dimensions() {
+		assert_eq!(Dimension::FORCE.to_string(), "kg*m/s^2");
+		assert_eq!((Dimension::LENGTH / Dimension::TIME).to_string(), "m/s");
+		assert_eq!(Dimension::NONE.to_string(), "1");
+	}
+}
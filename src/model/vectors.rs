@@ -0,0 +1,208 @@
+//! Vector expressions, where `p`, `v` and `a` are the 2D position, speed and acceleration of
+//! a point and `mean` the center of mass of the world. They are expanded into one scalar
+//! expression per component before being evaluated, so that a vector `d` is stored as its
+//! components `dx` and `dy`, like `p` is `px` and `py`.
+
+use super::parsing::{Expression, Program};
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Vectors available in expressions, each being the pair of variables `name` + `x` and `name` + `y`.
+pub const VECTORS: [&str; 4] = ["p", "v", "a", "mean"];
+
+/// Expression once its vectors are expanded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expanded {
+	Scalar (Expression),
+	Vector (Expression, Expression)
+}
+
+#[derive(Debug)]
+pub enum TypeError {
+	/// Added, subtracted or selected a vector and a scalar, in the written operation.
+	Mismatch (String),
+	/// Gave a vector to an operator or a function only accepting scalars.
+	NotScalar (String),
+	/// Gave a scalar to a function only accepting vectors.
+	NotVector (String),
+	/// The expression is a vector where its result is used as a scalar.
+	Unexpected (String)
+}
+
+/// Names of the components of the vector `name`.
+pub fn components(name: &str) -> (String, String) {
+	(format!("{name}x"), format!("{name}y"))
+}
+
+/// Expands `expression`, the names in `vectors` being vectors when true and scalars when
+/// false, and `VECTORS` being vectors unless `vectors` says otherwise.
+pub fn expand(expression: &Expression, vectors: &HashMap::<String, bool>) -> Result<Expanded, TypeError> {
+	use Expression::*;
+	use Expanded::{Scalar, Vector};
+
+	let scalar = |e: &Expression| match expand(e, vectors)? {
+		Scalar(s) => Ok(s),
+		Vector(..) => Err(TypeError::NotScalar(expression.to_string()))
+	};
+	let vector = |e: &Expression| match expand(e, vectors)? {
+		Vector(x, y) => Ok((x, y)),
+		Scalar(_) => Err(TypeError::NotVector(expression.to_string()))
+	};
+	let operation = |op: char, l: Expression, r: Expression| Operation(op, Box::new(l), Box::new(r));
+	let norm = |x: &Expression, y: &Expression| Call("sqrt".to_string(), vec![operation('+',
+		operation('^', x.clone(), Value(2f32)),
+		operation('^', y.clone(), Value(2f32))
+	)]);
+
+	match expression {
+		Value(_) | Quantity(..) => Ok(Scalar(expression.clone())),
+		Variable(name) => match vectors.get(name).copied().unwrap_or(VECTORS.contains(&name.as_str())) {
+			true => {
+				let (x, y) = components(name);
+				Ok(Vector(Variable(x), Variable(y)))
+			},
+			false => Ok(Scalar(expression.clone()))
+		},
+		Negation(e) => Ok(match expand(e, vectors)? {
+			Scalar(s) => Scalar(Negation(Box::new(s))),
+			Vector(x, y) => Vector(Negation(Box::new(x)), Negation(Box::new(y)))
+		}),
+		Not(e) => Ok(Scalar(Not(Box::new(scalar(e)?)))),
+		Operation(op, left, right) => match (*op, expand(left, vectors)?, expand(right, vectors)?) {
+			(op, Scalar(l), Scalar(r)) => Ok(Scalar(operation(op, l, r))),
+			// Componentwise operations
+			('+' | '-', Vector(lx, ly), Vector(rx, ry)) => Ok(Vector(operation(*op, lx, rx), operation(*op, ly, ry))),
+			('*', Scalar(s), Vector(x, y)) => Ok(Vector(operation('*', s.clone(), x), operation('*', s, y))),
+			('*' | '/', Vector(x, y), Scalar(s)) => Ok(Vector(operation(*op, x, s.clone()), operation(*op, y, s))),
+			('+' | '-', ..) => Err(TypeError::Mismatch(expression.to_string())),
+			_ => Err(TypeError::NotScalar(expression.to_string()))
+		},
+		Call(name, args) => match (name.as_str(), args.as_slice()) {
+			("vec", [x, y]) => Ok(Vector(scalar(x)?, scalar(y)?)),
+			("norm", [u]) => {
+				let (x, y) = vector(u)?;
+				Ok(Scalar(norm(&x, &y)))
+			},
+			("dot", [u, w]) => {
+				let ((ux, uy), (wx, wy)) = (vector(u)?, vector(w)?);
+				Ok(Scalar(operation('+', operation('*', ux, wx), operation('*', uy, wy))))
+			},
+			("normalize", [u]) => {
+				let (x, y) = vector(u)?;
+				let n = norm(&x, &y);
+				Ok(Vector(operation('/', x, n.clone()), operation('/', y, n)))
+			},
+			// Sums each component over the world
+			("sum", [u]) => Ok(match expand(u, vectors)? {
+				Scalar(s) => Scalar(Call(name.clone(), vec![s])),
+				Vector(x, y) => Vector(Call(name.clone(), vec![x]), Call(name.clone(), vec![y]))
+			}),
+			// Selects between two vectors componentwise
+			("if", [condition, a, b]) => match (scalar(condition)?, expand(a, vectors)?, expand(b, vectors)?) {
+				(c, Scalar(a), Scalar(b)) => Ok(Scalar(Call(name.clone(), vec![c, a, b]))),
+				(c, Vector(ax, ay), Vector(bx, by)) => Ok(Vector(
+					Call(name.clone(), vec![c.clone(), ax, bx]),
+					Call(name.clone(), vec![c, ay, by])
+				)),
+				_ => Err(TypeError::Mismatch(expression.to_string()))
+			},
+			_ => Ok(Scalar(Call(name.clone(), args.iter().map(scalar).collect::<Result<Vec::<Expression>, TypeError>>()?)))
+		}
+	}
+}
+
+/// Expands `expression`, which must be a scalar.
+pub fn expand_scalar(expression: &Expression) -> Result<Expression, TypeError> {
+	match expand(expression, &HashMap::new())? {
+		Expanded::Scalar(e) => Ok(e),
+		Expanded::Vector(..) => Err(TypeError::Unexpected(expression.to_string()))
+	}
+}
+
+/// Expands the statements of `program`, a vector statement binding each of its components.
+/// A vector bound to one of `outputs` binds `x` and `y` instead, so that a force script may
+/// give both components at once.
+pub fn expand_program(program: &Program, outputs: &[&str]) -> Result<Program, TypeError> {
+	let mut vectors = HashMap::<String, bool>::new();
+	let mut bindings = Vec::<(String, Expression)>::new();
+
+	for (name, expression) in program.bindings.iter() {
+		match expand(expression, &vectors)? {
+			Expanded::Scalar(e) => {
+				vectors.insert(name.clone(), false);
+				bindings.push((name.clone(), e));
+			},
+			Expanded::Vector(x, y) => {
+				let (x_name, y_name) = match outputs.contains(&name.as_str()) {
+					true => ("x".to_string(), "y".to_string()),
+					false => components(name)
+				};
+				vectors.insert(name.clone(), !outputs.contains(&name.as_str()));
+				vectors.insert(x_name.clone(), false);
+				vectors.insert(y_name.clone(), false);
+				bindings.push((x_name, x));
+				bindings.push((y_name, y));
+			}
+		}
+	}
+
+	Ok(Program {bindings})
+}
+
+impl fmt::Display for TypeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			TypeError::Mismatch(operation) => write!(f, "{operation} mixes a vector and a scalar"),
+			TypeError::NotScalar(e) => write!(f, "{e} only accepts scalars"),
+			TypeError::NotVector(e) => write!(f, "{e} only accepts vectors"),
+			TypeError::Unexpected(e) => write!(f, "{e} is a vector, expected a scalar")
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::parsing::Parser;
+	use super::super::tokening::Tokenizer;
+
+	fn parse(raw: &str) -> Expression {
+		Parser::build(&Tokenizer::tokenize_with_functions(raw, &[]).unwrap()).unwrap()
+	}
+
+	fn program(raw: &str) -> Program {
+		Program::build(&Tokenizer::tokenize_with_functions(raw, &[]).unwrap(), "x").unwrap()
+	}
+
+	#[test]
+	fn expands_componentwise() {
+		assert_eq!(
+			expand(&parse("2(p - mean)"), &HashMap::new()).unwrap(),
+			Expanded::Vector(parse("2*(px - meanx)"), parse("2*(py - meany)"))
+		);
+		assert_eq!(expand_scalar(&parse("dot(p, v)")).unwrap(), parse("px*vx + py*vy"));
+		assert_eq!(expand_scalar(&parse("norm(vec(3, 4))")).unwrap().evaluate(&Default::default()).unwrap(), 5f32);
+	}
+
+	#[test]
+	fn refuses_shape_mismatches() {
+		assert!(matches!(expand_scalar(&parse("p + 1")), Err(TypeError::Mismatch(_))));
+		assert!(matches!(expand_scalar(&parse("if(px > 0, p, 0)")), Err(TypeError::Mismatch(_))));
+		assert!(matches!(expand_scalar(&parse("p*v")), Err(TypeError::NotScalar(_))));
+		assert!(matches!(expand_scalar(&parse("sin(p)")), Err(TypeError::NotScalar(_))));
+		assert!(matches!(expand_scalar(&parse("norm(px)")), Err(TypeError::NotVector(_))));
+		assert!(matches!(expand_scalar(&parse("p")), Err(TypeError::Unexpected(_))));
+	}
+
+	#[test]
+	fn binds_vector_statements_to_their_components() {
+		assert_eq!(
+			expand_program(&program("d = p - mean; -d"), &["x", "y"]).unwrap(),
+			program("dx = px - meanx; dy = py - meany; x = -dx; y = -dy")
+		);
+		// A name bound to a scalar hides the vector of the same name
+		assert!(expand_program(&program("p = 2; p + 1"), &["x", "y"]).is_ok());
+		assert!(matches!(expand_program(&program("d = p; d + 1"), &["x", "y"]), Err(TypeError::Mismatch(_))));
+	}
+}
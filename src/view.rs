@@ -1,3 +1,5 @@
-
 pub mod application;
-pub mod widgets;
\ No newline at end of file
+pub mod widgets;
+pub mod grid;
+pub mod field;
+pub mod traces;
\ No newline at end of file
@@ -5,8 +5,74 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use raylib::prelude::*;
 use super::super::model::physics::*;
+use super::super::model::diagnostics::*;
 use super::widgets::*;
+use super::grid::*;
+use super::field::*;
+use super::traces::*;
 use super::super::model::tokening::*;
+use super::super::model::parsing::{Expression, FunctionLibrary, Parser, Program, VariableContext};
+use super::super::model::units::Dimension;
+use super::super::model::vectors::{expand_program, expand_scalar};
+use super::super::model::triggers::{Event, Trigger};
+
+/// Messages émis par les widgets de l'application lorsqu'ils sont activés.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+	AddPoint,
+	AddForce,
+	SelectPoint (usize),
+	TeleportPoint,
+	SetPointSpeed,
+	SetPointMass,
+	TogglePointTrail,
+	DeletePoint,
+	ClosePointMenu,
+	EditForce (String),
+	TogglePause,
+	Step,
+	ToggleFollow,
+	FitAllPoints,
+	ResetCamera,
+	ToggleGrid,
+	ToggleSpeedArrows,
+	ToggleAccelerationArrows,
+	ToggleForceArrows,
+	ToggleArrowsOnAllPoints,
+	ApplyForce,
+	PreviewField,
+	CreateForce,
+	CancelForceCreation,
+	ShowTrailMenu,
+	CloseTrailMenu,
+	SetSelectedTrailLength,
+	SetAllTrailLengths,
+	CycleTrailColoring,
+	ToggleTrailFade,
+	ShowPlotMenu,
+	ClosePlotMenu,
+	SetPlotExpressions,
+	ShowDiagnostics,
+	CloseDiagnostics,
+	ResetDrift,
+	DeriveForceFromPotential,
+	ShowParameters,
+	CloseParameters,
+	AddParameters,
+	RemoveParameter (String),
+	ShowFunctions,
+	CloseFunctions,
+	AddFunction,
+	RemoveFunction (String),
+	ShowState,
+	CloseState,
+	SetStateVariables,
+	SetPointState,
+	ShowTriggers,
+	CloseTriggers,
+	AddTrigger,
+	RemoveTrigger (String)
+}
 
 /// Gère les evenements, les visuels et les simulations
 /// # Exemple
@@ -24,10 +90,37 @@ use super::super::model::tokening::*;
 /// ```
 pub struct Application {
 	world: World,
-	selected_point: i32,
+	selected_point: Option<usize>,
+	dragged_point: Option<usize>,
+	drag_offset: Vector2,
+	drag_speed: Vector2,
+
+	paused: bool,
+	step_accumulator: f32,
+
+	camera: Camera2D,
+	follow_selected: bool,
+	grid: Grid,
+	arrows: ArrowOptions,
+	arrows_on_all_points: bool,
+	field: Option<ForceField>,
+	trail_options: TrailOptions,
+	default_trail_length: Option<usize>,
+	traces: Option<TraceLayer>,
+	// Expressions plotted for the selected point, the abscissa being the time when None
+	plot_x: Option<Expression>,
+	plot_series: Vec::<Expression>,
+	plotted_point: Option<usize>,
+	diagnostics: Option<Diagnostics>,
+	// Diagnostics the drift is measured from, taken again after the user edits the world
+	diagnostics_reference: Option<Diagnostics>,
 
 	forces: Rc::<RefCell::<HashMap::<String, Force>>>,
 	selected_force: String,
+	// World-level parameters, time and random seed, shared by every point
+	environment: Rc::<RefCell::<Environment>>,
+	// User functions, inlined in forces when they are applied
+	functions: FunctionLibrary,
 
 	rl_handle: RaylibHandle,
 	rl_thread: RaylibThread,
@@ -35,31 +128,77 @@ pub struct Application {
 	// TODO: Put all of those in a struct with an iterator
 	// so that we could just iterate when calling the same function
 	// on every WidgetTree.
-	inspector: WidgetTree,
-	force_menu: WidgetTree,
-	contextual_menu: WidgetTree,
-	force_inspector: WidgetTree,
-	force_naming: WidgetTree,
+	inspector: WidgetTree<Message>,
+	force_menu: WidgetTree<Message>,
+	contextual_menu: WidgetTree<Message>,
+	force_inspector: WidgetTree<Message>,
+	force_naming: WidgetTree<Message>,
+	point_menu: WidgetTree<Message>,
+	sim_controls: WidgetTree<Message>,
+	camera_controls: WidgetTree<Message>,
+	arrow_controls: WidgetTree<Message>,
+	trail_menu: WidgetTree<Message>,
+	plot_menu: WidgetTree<Message>,
+	diagnostics_panel: WidgetTree<Message>,
+	parameters_panel: WidgetTree<Message>,
+	functions_panel: WidgetTree<Message>,
+	state_panel: WidgetTree<Message>,
+	triggers_panel: WidgetTree<Message>,
 
 	force_menu_just_appeared: bool
 }
 
 impl Application {
+	const MIN_ZOOM: f32 = 0.05f32;
+	const MAX_ZOOM: f32 = 20f32;
+
 	pub fn realize() -> Self {
 		let (mut rl_handle, rl_thread) = raylib::init()
 										.size(800, 450)
 										.title("Expressive Physics")
 										.build();
 		rl_handle.set_target_fps(60);
+
+		let trail_options = TrailOptions {coloring: TrailColoring::Time, thickness: 10f32, fade: true};
+		let traces = match TraceLayer::new(&mut rl_handle, &rl_thread, trail_options) {
+			Ok(t) => Some(t),
+			Err(e) => {
+				println!("Error: persistent trails unavailable : {e}.");
+				None
+			}
+		};
 		
 		Application {
 			world: World::new(),
-			selected_point: -1i32,
+			selected_point: None,
+			dragged_point: None,
+			drag_offset: Vector2::zero(),
+			drag_speed: Vector2::zero(),
+
+			paused: false,
+			step_accumulator: 0f32,
+
+			camera: Self::default_camera(),
+			follow_selected: false,
+			grid: Grid::new(),
+			arrows: ArrowOptions {speed: false, acceleration: false, forces: false},
+			arrows_on_all_points: false,
+			field: None,
+			trail_options,
+			default_trail_length: Some(Point::DEFAULT_TRAIL_LENGTH),
+			traces,
+			plot_x: None,
+			plot_series: Vec::<Expression>::new(),
+			plotted_point: None,
+			diagnostics: None,
+			diagnostics_reference: None,
 
 			forces: Rc::<RefCell::<HashMap::<String, Force>>>::new(
 				RefCell::new(HashMap::<String, Force>::new())
 			),
 			selected_force: String::new(),
+			environment: Rc::<RefCell::<Environment>>::new(RefCell::new(Environment::default())),
+			functions: FunctionLibrary::default(),
 
 			rl_handle,
 			rl_thread,
@@ -84,13 +223,57 @@ impl Application {
 				Self::build_default_force_naming(),
 				Layout::new(Vector2::new(400f32, 225f32), Vector2::new(600f32, 100f32))
 			),
+			point_menu: WidgetTree::new(
+				Self::build_default_point_menu(),
+				Layout::new(Vector2::new(400f32, 350f32), Vector2::new(400f32, 180f32))
+			),
+			sim_controls: WidgetTree::new(
+				Self::build_default_sim_controls(),
+				Layout::new(Vector2::new(400f32, 20f32), Vector2::new(360f32, 30f32))
+			),
+			camera_controls: WidgetTree::new(
+				Self::build_default_camera_controls(),
+				Layout::new(Vector2::new(700f32, 437f32), Vector2::new(200f32, 22f32))
+			),
+			arrow_controls: WidgetTree::new(
+				Self::build_default_arrow_controls(),
+				Layout::new(Vector2::new(700f32, 12f32), Vector2::new(200f32, 22f32))
+			),
+			trail_menu: WidgetTree::new(
+				Self::build_default_trail_menu(),
+				Layout::new(Vector2::new(400f32, 225f32), Vector2::new(300f32, 200f32))
+			),
+			plot_menu: WidgetTree::new(
+				Self::build_default_plot_menu(),
+				Layout::new(Vector2::new(400f32, 125f32), Vector2::new(400f32, 180f32))
+			),
+			diagnostics_panel: WidgetTree::new(
+				Self::build_default_diagnostics_panel(),
+				Layout::new(Vector2::new(400f32, 300f32), Vector2::new(340f32, 140f32))
+			),
+			parameters_panel: WidgetTree::new(
+				Self::build_default_parameters_panel(),
+				Layout::new(Vector2::new(400f32, 240f32), Vector2::new(360f32, 260f32))
+			),
+			functions_panel: WidgetTree::new(
+				Self::build_default_functions_panel(),
+				Layout::new(Vector2::new(400f32, 240f32), Vector2::new(360f32, 260f32))
+			),
+			state_panel: WidgetTree::new(
+				Self::build_default_state_panel(),
+				Layout::new(Vector2::new(400f32, 240f32), Vector2::new(360f32, 260f32))
+			),
+			triggers_panel: WidgetTree::new(
+				Self::build_default_triggers_panel(),
+				Layout::new(Vector2::new(400f32, 240f32), Vector2::new(420f32, 260f32))
+			),
 
 			force_menu_just_appeared: false
 
 		}
 	}
 
-	fn build_default_inspector() -> Widget {
+	fn build_default_inspector() -> Widget<Message> {
 		Widget::new(
 			Layout::new(
 				Vector2::new(0f32,0f32),
@@ -113,7 +296,7 @@ impl Application {
 		)
 	}
 
-	fn build_default_contextual_menu() -> Widget {
+	fn build_default_contextual_menu() -> Widget<Message> {
 		Widget::new(
 			Layout::new(
 				Vector2::new(0f32, 0f32),
@@ -129,7 +312,7 @@ impl Application {
 				WidgetVariant::Button {
 					state: ButtonState::Rest
 				}
-			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).id("add point".to_string())
+			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).on_activation(Message::AddPoint)
 			.add_child(
 				Widget::new(
 					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(1f32, 1f32)),
@@ -144,7 +327,7 @@ impl Application {
 				WidgetVariant::Button {
 					state: ButtonState::Rest
 				}
-			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).id("add force".to_string())
+			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).on_activation(Message::AddForce)
 			.add_child(
 				Widget::new(
 					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(1f32, 1f32)),
@@ -153,10 +336,115 @@ impl Application {
 			)
 
 		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, -0.23f32), Vector2::new(1f32, 0.1f32)),
+				WidgetVariant::Button {
+					state: ButtonState::Rest
+				}
+			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).on_activation(Message::ShowTrailMenu)
+			.add_child(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(1f32, 1f32)),
+					WidgetVariant::Label {text: "Trails".to_string(), font_size: 16i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, -0.12f32), Vector2::new(1f32, 0.1f32)),
+				WidgetVariant::Button {
+					state: ButtonState::Rest
+				}
+			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).on_activation(Message::ShowPlotMenu)
+			.add_child(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(1f32, 1f32)),
+					WidgetVariant::Label {text: "Plot".to_string(), font_size: 16i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, -0.01f32), Vector2::new(1f32, 0.1f32)),
+				WidgetVariant::Button {
+					state: ButtonState::Rest
+				}
+			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).on_activation(Message::ShowDiagnostics)
+			.add_child(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(1f32, 1f32)),
+					WidgetVariant::Label {text: "Diagnostics".to_string(), font_size: 16i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, 0.1f32), Vector2::new(1f32, 0.1f32)),
+				WidgetVariant::Button {
+					state: ButtonState::Rest
+				}
+			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).on_activation(Message::ShowParameters)
+			.add_child(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(1f32, 1f32)),
+					WidgetVariant::Label {text: "Parameters".to_string(), font_size: 16i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, 0.21f32), Vector2::new(1f32, 0.1f32)),
+				WidgetVariant::Button {
+					state: ButtonState::Rest
+				}
+			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).on_activation(Message::ShowFunctions)
+			.add_child(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(1f32, 1f32)),
+					WidgetVariant::Label {text: "Functions".to_string(), font_size: 16i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, 0.32f32), Vector2::new(1f32, 0.1f32)),
+				WidgetVariant::Button {
+					state: ButtonState::Rest
+				}
+			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).on_activation(Message::ShowState)
+			.add_child(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(1f32, 1f32)),
+					WidgetVariant::Label {text: "State".to_string(), font_size: 16i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, 0.43f32), Vector2::new(1f32, 0.1f32)),
+				WidgetVariant::Button {
+					state: ButtonState::Rest
+				}
+			).style(Style::default().foreground(Color::GREEN).action(Color::GRAY)).on_activation(Message::ShowTriggers)
+			.add_child(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(1f32, 1f32)),
+					WidgetVariant::Label {text: "Triggers".to_string(), font_size: 16i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+
+		)
 
 	}
 
-	fn build_default_force_menu() -> Widget {
+	fn build_default_force_menu() -> Widget<Message> {
 		Widget::new(
 			Layout::new(
 				Vector2::new(0f32, 0f32),
@@ -180,11 +468,11 @@ impl Application {
 		)
 		.add_child(
 			Widget::new(
-				Layout::new(Vector2::new(0f32, -0.15f32), Vector2::new(0.8f32, 0.15f32)),
+				Layout::new(Vector2::new(0f32, -0.2f32), Vector2::new(0.8f32, 0.13f32)),
 				WidgetVariant::TextInput {
 					selected: false,
 					text: String::new(),
-					placeholder: "Acceleration X".to_string(),
+					placeholder: "Force X, or script".to_string(),
 					cursor: 0u32,
 					registered: true
 				}
@@ -192,11 +480,11 @@ impl Application {
 		)
 		.add_child(
 			Widget::new(
-				Layout::new(Vector2::new(0f32, 0.05f32), Vector2::new(0.8f32, 0.15f32)),
+				Layout::new(Vector2::new(0f32, -0.03f32), Vector2::new(0.8f32, 0.13f32)),
 				WidgetVariant::TextInput {
 					selected: false,
 					text: String::new(),
-					placeholder: "Acceleration Y".to_string(),
+					placeholder: "Force Y".to_string(),
 					cursor: 0u32,
 					registered: true
 				}
@@ -204,10 +492,31 @@ impl Application {
 		)
 		.add_child(
 			Widget::new(
-				Layout::new(Vector2::new(0f32, 0.3f32), Vector2::new(0.4f32, 0.2f32)),
+				Layout::new(Vector2::new(0f32, 0.14f32), Vector2::new(0.8f32, 0.13f32)),
+				WidgetVariant::TextInput {
+					selected: false,
+					text: String::new(),
+					placeholder: "Potential energy (optional)".to_string(),
+					cursor: 0u32,
+					registered: true
+				}
+			).id("set u".to_string())
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, 0.245f32), Vector2::new(0.8f32, 0.06f32)),
+				WidgetVariant::Label {text: String::new(), font_size: 12i32}
+			).style(Style::default()
+			.background(Color::new(0, 0, 0, 0))
+			.foreground(Color::RED))
+			.id("force error".to_string())
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.3f32, 0.36f32), Vector2::new(0.25f32, 0.18f32)),
 				WidgetVariant::Button {state: ButtonState::Rest}
 			).style(Style::default())
-			.id("apply".to_string())
+			.on_activation(Message::ApplyForce)
 			.add_child(
 				Widget::new(
 					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(0.9f32, 0.9f32)),
@@ -215,10 +524,36 @@ impl Application {
 				).style(Style::default().background(Color::new(0, 0, 0, 0)))
 			)
 		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, 0.36f32), Vector2::new(0.25f32, 0.18f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			).style(Style::default())
+			.on_activation(Message::PreviewField)
+			.add_child(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(0.9f32, 0.9f32)),
+					WidgetVariant::Label {text: "Field".to_string(), font_size: 16i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0.3f32, 0.36f32), Vector2::new(0.25f32, 0.18f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			).style(Style::default())
+			.on_activation(Message::DeriveForceFromPotential)
+			.add_child(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, 0f32), Vector2::new(0.9f32, 0.9f32)),
+					WidgetVariant::Label {text: "From potential".to_string(), font_size: 16i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+		)
 		
 	}
 
-	fn build_default_force_inspector() -> Widget {
+	fn build_default_force_inspector() -> Widget<Message> {
 		Widget::new(
 			Layout::new(
 				Vector2::new(0f32,0f32),
@@ -241,7 +576,7 @@ impl Application {
 		)
 	}
 
-	fn build_default_force_naming() -> Widget {
+	fn build_default_force_naming() -> Widget<Message> {
 		Widget::new(
 			Layout::default(),
 			WidgetVariant::Frame {outline_thickness: 1f32}
@@ -275,7 +610,7 @@ impl Application {
 				WidgetVariant::Button {state: ButtonState::Rest}
 			)
 			.style(Style::default().action(Color::GREEN))
-			.id(String::from("create"))
+			.on_activation(Message::CreateForce)
 			.add_child(
 				Widget::new(
 					Layout::default(),
@@ -291,7 +626,7 @@ impl Application {
 				),
 				WidgetVariant::Button {state: ButtonState::Rest}
 			)
-			.id(String::from("cancel"))
+			.on_activation(Message::CancelForceCreation)
 			.style(Style::default().action(Color::RED))
 			.add_child(
 				Widget::new(
@@ -302,183 +637,1987 @@ impl Application {
 		)
 	}
 
-	pub fn mainloop(&mut self) {
-		while !self.rl_handle.window_should_close() {
-
-			for p in self.world.iter_mut() {
-				p.simulate();
-			}
+	fn build_default_point_menu() -> Widget<Message> {
+		Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.hidden()
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.25f32, -0.41f32), Vector2::new(0.45f32, 0.14f32)),
+				WidgetVariant::Label {text: String::new(), font_size: 20i32}
+			)
+			.style(Style::default().background(Color::new(0, 0, 0, 0)))
+			.id(String::from("point title"))
+		)
+		.add_child(Self::build_point_menu_label(-0.27f32, "point position"))
+		.add_child(Self::build_point_menu_label(-0.16f32, "point speed"))
+		.add_child(Self::build_point_menu_label(-0.05f32, "point acceleration"))
+		.add_child(Self::build_point_menu_label(0.06f32, "point mass"))
+		.add_child(Self::build_point_menu_label(0.17f32, "point forces"))
+		.add_child(Self::build_point_menu_label(0.26f32, "point state"))
+		.add_child(Self::build_point_menu_input(Vector2::new(0.13f32, -0.36f32), "Position X", "teleport x"))
+		.add_child(Self::build_point_menu_input(Vector2::new(0.37f32, -0.36f32), "Position Y", "teleport y"))
+		.add_child(Self::build_point_menu_button(Vector2::new(0.25f32, -0.19f32), Vector2::new(0.46f32, 0.13f32), "Teleport", Message::TeleportPoint))
+		.add_child(Self::build_point_menu_input(Vector2::new(0.13f32, 0.0f32), "Speed X", "speed x"))
+		.add_child(Self::build_point_menu_input(Vector2::new(0.37f32, 0.0f32), "Speed Y", "speed y"))
+		.add_child(Self::build_point_menu_button(Vector2::new(0.25f32, 0.17f32), Vector2::new(0.46f32, 0.13f32), "Set speed", Message::SetPointSpeed))
+		.add_child(Self::build_point_menu_button(Vector2::new(-0.37f32, 0.38f32), Vector2::new(0.2f32, 0.15f32), "Delete", Message::DeletePoint))
+		.add_child(Self::build_point_menu_button(Vector2::new(-0.13f32, 0.38f32), Vector2::new(0.2f32, 0.15f32), "Trail", Message::TogglePointTrail))
+		.add_child(Self::build_point_menu_input(Vector2::new(0.13f32, 0.38f32), "Mass", "mass"))
+		.add_child(Self::build_point_menu_button(Vector2::new(0.37f32, 0.38f32), Vector2::new(0.22f32, 0.13f32), "Set mass", Message::SetPointMass))
+		.add_child(Self::build_point_menu_button(Vector2::new(0.46f32, -0.42f32), Vector2::new(0.06f32, 0.12f32), "X", Message::ClosePointMenu))
+	}
 
-			self.handle_events();
-			self.draw();
-		}
+	fn build_default_sim_controls() -> Widget<Message> {
+		Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.41f32, 0f32), Vector2::new(0.16f32, 0.8f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			)
+			.on_activation(Message::TogglePause)
+			.add_child(
+				Widget::new(
+					Layout::default(),
+					WidgetVariant::Label {text: String::from("Pause"), font_size: 14i32}
+				)
+				.style(Style::default().background(Color::new(0, 0, 0, 0)))
+				.id(String::from("pause label"))
+			)
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.24f32, 0f32), Vector2::new(0.16f32, 0.8f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			)
+			.on_activation(Message::Step)
+			.add_child(
+				Widget::new(
+					Layout::default(),
+					WidgetVariant::Label {text: String::from("Step"), font_size: 14i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.02f32, 0f32), Vector2::new(0.24f32, 0.6f32)),
+				WidgetVariant::Slider {value: 1f32, min: 0f32, max: 4f32, grabbed: false}
+			)
+			.style(Style::default().action(Color::GRAY))
+			.id(String::from("speed"))
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0.3f32, 0f32), Vector2::new(0.4f32, 1f32)),
+				WidgetVariant::Label {text: String::new(), font_size: 12i32}
+			)
+			.style(Style::default().background(Color::new(0, 0, 0, 0)))
+			.id(String::from("sim status"))
+		)
+	}
 
-		println!("Application closed successfuly :)");
+	fn build_default_camera_controls() -> Widget<Message> {
+		Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.375f32, 0f32), Vector2::new(0.24f32, 0.9f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			)
+			.on_activation(Message::ToggleFollow)
+			.add_child(
+				Widget::new(
+					Layout::default(),
+					WidgetVariant::Label {text: String::from("Follow"), font_size: 12i32}
+				)
+				.style(Style::default().background(Color::new(0, 0, 0, 0)))
+				.id(String::from("follow label"))
+			)
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.125f32, 0f32), Vector2::new(0.24f32, 0.9f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			)
+			.on_activation(Message::FitAllPoints)
+			.add_child(
+				Widget::new(
+					Layout::default(),
+					WidgetVariant::Label {text: String::from("Fit all"), font_size: 12i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0.125f32, 0f32), Vector2::new(0.24f32, 0.9f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			)
+			.on_activation(Message::ResetCamera)
+			.add_child(
+				Widget::new(
+					Layout::default(),
+					WidgetVariant::Label {text: String::from("Reset"), font_size: 12i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0.375f32, 0f32), Vector2::new(0.24f32, 0.9f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			)
+			.on_activation(Message::ToggleGrid)
+			.add_child(
+				Widget::new(
+					Layout::default(),
+					WidgetVariant::Label {text: String::from("Grid"), font_size: 12i32}
+				).style(Style::default().background(Color::new(0, 0, 0, 0)))
+			)
+		)
 	}
 
-	fn handle_events(&mut self) {
+	fn build_default_arrow_controls() -> Widget<Message> {
+		let mut bar = Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)));
 
-		// Make widget trees hear events
-		self.inspector.check_event(&mut self.rl_handle);
-		self.force_inspector.check_event(&mut self.rl_handle);
-		self.force_menu.check_event(&mut self.rl_handle);
-		self.force_naming.check_event(&mut self.rl_handle);
-		self.contextual_menu.check_event(&mut self.rl_handle);
+		let buttons = [
+			("speed arrows label", Message::ToggleSpeedArrows),
+			("acceleration arrows label", Message::ToggleAccelerationArrows),
+			("force arrows label", Message::ToggleForceArrows),
+			("arrows scope label", Message::ToggleArrowsOnAllPoints)
+		];
+		for (i, (id, message)) in buttons.into_iter().enumerate() {
+			bar.add_child_inplace(
+				Widget::new(
+					Layout::new(Vector2::new(-0.375f32 + 0.25f32 * i as f32, 0f32), Vector2::new(0.24f32, 0.9f32)),
+					WidgetVariant::Button {state: ButtonState::Rest}
+				)
+				.on_activation(message)
+				.add_child(
+					Widget::new(
+						Layout::default(),
+						WidgetVariant::Label {text: String::new(), font_size: 12i32}
+					)
+					.style(Style::default().background(Color::new(0, 0, 0, 0)))
+					.id(id.to_string())
+				)
+			);
+		}
 
-		// Special behaviours
-		self.contextual_menu_events();
-		self.inspector_events();
-		self.force_inspector_events();
+		bar
+	}
+
+	fn build_default_trail_menu() -> Widget<Message> {
+		Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.hidden()
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, -0.4f32), Vector2::new(0.6f32, 0.14f32)),
+				WidgetVariant::Label {text: String::from("Trails"), font_size: 20i32}
+			)
+			.style(Style::default().background(Color::new(0, 0, 0, 0)))
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, -0.2f32), Vector2::new(0.8f32, 0.13f32)),
+				WidgetVariant::TextInput {
+					selected: false,
+					text: String::new(),
+					placeholder: String::from("Length (0: persistent)"),
+					cursor: 0u32,
+					registered: true
+				}
+			)
+			.id(String::from("trail length"))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(-0.2f32, -0.02f32), Vector2::new(0.38f32, 0.13f32), "Set on selected", Message::SetSelectedTrailLength))
+		.add_child(Self::build_point_menu_button(Vector2::new(0.2f32, -0.02f32), Vector2::new(0.38f32, 0.13f32), "Set on all", Message::SetAllTrailLengths))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.2f32, 0.18f32), Vector2::new(0.38f32, 0.13f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			)
+			.on_activation(Message::CycleTrailColoring)
+			.add_child(
+				Widget::new(
+					Layout::default(),
+					WidgetVariant::Label {text: String::new(), font_size: 14i32}
+				)
+				.style(Style::default().background(Color::new(0, 0, 0, 0)))
+				.id(String::from("trail coloring label"))
+			)
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0.2f32, 0.18f32), Vector2::new(0.38f32, 0.13f32)),
+				WidgetVariant::Button {state: ButtonState::Rest}
+			)
+			.on_activation(Message::ToggleTrailFade)
+			.add_child(
+				Widget::new(
+					Layout::default(),
+					WidgetVariant::Label {text: String::new(), font_size: 14i32}
+				)
+				.style(Style::default().background(Color::new(0, 0, 0, 0)))
+				.id(String::from("trail fade label"))
+			)
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.3f32, 0.38f32), Vector2::new(0.2f32, 0.1f32)),
+				WidgetVariant::Label {text: String::from("Width"), font_size: 14i32}
+			)
+			.style(Style::default().background(Color::new(0, 0, 0, 0)))
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0.1f32, 0.38f32), Vector2::new(0.56f32, 0.08f32)),
+				WidgetVariant::Slider {value: 10f32, min: 1f32, max: 20f32, grabbed: false}
+			)
+			.style(Style::default().action(Color::GRAY))
+			.id(String::from("trail thickness"))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.44f32, -0.41f32), Vector2::new(0.08f32, 0.13f32), "X", Message::CloseTrailMenu))
+	}
+
+	fn build_default_plot_menu() -> Widget<Message> {
+		Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.hidden()
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, -0.14f32), Vector2::new(0.96f32, 0.66f32)),
+				WidgetVariant::Plot {series: Vec::<PlotSeries>::new(), window: 500usize}
+			)
+			.style(Style::default().background(Color::WHITE).action(Color::LIGHTGRAY))
+			.id(String::from("plot"))
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.32f32, 0.29f32), Vector2::new(0.3f32, 0.12f32)),
+				WidgetVariant::TextInput {
+					selected: false,
+					text: String::new(),
+					placeholder: String::from("X (empty: t)"),
+					cursor: 0u32,
+					registered: true
+				}
+			)
+			.id(String::from("plot x"))
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0.1f32, 0.29f32), Vector2::new(0.5f32, 0.12f32)),
+				WidgetVariant::TextInput {
+					selected: false,
+					text: String::new(),
+					placeholder: String::from("Y (a; b; ...)"),
+					cursor: 0u32,
+					registered: true
+				}
+			)
+			.id(String::from("plot y"))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.42f32, 0.29f32), Vector2::new(0.12f32, 0.12f32), "Plot", Message::SetPlotExpressions))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.36f32, 0.43f32), Vector2::new(0.2f32, 0.1f32)),
+				WidgetVariant::Label {text: String::from("Window"), font_size: 14i32}
+			)
+			.style(Style::default().background(Color::new(0, 0, 0, 0)))
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0.06f32, 0.43f32), Vector2::new(0.6f32, 0.08f32)),
+				WidgetVariant::Slider {value: 500f32, min: 50f32, max: 2000f32, grabbed: false}
+			)
+			.style(Style::default().action(Color::GRAY))
+			.id(String::from("plot window"))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.44f32, 0.43f32), Vector2::new(0.08f32, 0.1f32), "X", Message::ClosePlotMenu))
+	}
+
+	fn build_default_diagnostics_panel() -> Widget<Message> {
+		let mut panel = Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.hidden()
+		.add_child(Self::build_point_menu_button(Vector2::new(-0.36f32, 0.4f32), Vector2::new(0.24f32, 0.14f32), "Reset drift", Message::ResetDrift))
+		.add_child(Self::build_point_menu_button(Vector2::new(0.45f32, -0.41f32), Vector2::new(0.06f32, 0.14f32), "X", Message::CloseDiagnostics));
+
+		let ids = ["kinetic energy", "potential energy", "total energy", "momentum", "angular momentum"];
+		for (i, id) in ids.into_iter().enumerate() {
+			panel.add_child_inplace(
+				Widget::new(
+					Layout::new(Vector2::new(-0.05f32, -0.4f32 + 0.14f32 * i as f32), Vector2::new(0.9f32, 0.12f32)),
+					WidgetVariant::Label {text: String::new(), font_size: 14i32}
+				)
+				.style(Style::default().background(Color::new(0, 0, 0, 0)))
+				.id(id.to_string())
+			);
+		}
+
+		panel
+	}
+
+	fn build_default_parameters_panel() -> Widget<Message> {
+		Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.hidden()
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.2f32, -0.44f32), Vector2::new(0.5f32, 0.08f32)),
+				WidgetVariant::Label {text: String::from("Parameters"), font_size: 16i32}
+			)
+			.style(Style::default().background(Color::new(0, 0, 0, 0)))
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0.22f32, -0.44f32), Vector2::new(0.3f32, 0.08f32)),
+				WidgetVariant::TextInput {
+					selected: false,
+					text: String::new(),
+					placeholder: String::from("Seed: 0"),
+					cursor: 0u32,
+					registered: true
+				}
+			)
+			.id(String::from("random seed"))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.45f32, -0.44f32), Vector2::new(0.06f32, 0.08f32), "X", Message::CloseParameters))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.12f32, -0.32f32), Vector2::new(0.7f32, 0.1f32)),
+				WidgetVariant::TextInput {
+					selected: false,
+					text: String::new(),
+					placeholder: String::from("k = 0.5; g = 9.81"),
+					cursor: 0u32,
+					registered: true
+				}
+			)
+			.id(String::from("parameter definition"))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.37f32, -0.32f32), Vector2::new(0.2f32, 0.1f32), "Set", Message::AddParameters))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, 0.14f32), Vector2::new(0.96f32, 0.76f32)),
+				WidgetVariant::Scroll {offset: 0f32}
+			)
+			.id(String::from("parameter rows"))
+		)
+	}
+
+	fn build_default_functions_panel() -> Widget<Message> {
+		Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.hidden()
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.2f32, -0.44f32), Vector2::new(0.5f32, 0.08f32)),
+				WidgetVariant::Label {text: String::from("Functions"), font_size: 16i32}
+			)
+			.style(Style::default().background(Color::new(0, 0, 0, 0)))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.45f32, -0.44f32), Vector2::new(0.06f32, 0.08f32), "X", Message::CloseFunctions))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.12f32, -0.32f32), Vector2::new(0.7f32, 0.1f32)),
+				WidgetVariant::TextInput {
+					selected: false,
+					text: String::new(),
+					placeholder: String::from("spring(d, k) = -k*d"),
+					cursor: 0u32,
+					registered: true
+				}
+			)
+			.id(String::from("function definition"))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.37f32, -0.32f32), Vector2::new(0.2f32, 0.1f32), "Set", Message::AddFunction))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, -0.21f32), Vector2::new(0.96f32, 0.06f32)),
+				WidgetVariant::Label {text: String::new(), font_size: 12i32}
+			).style(Style::default()
+			.background(Color::new(0, 0, 0, 0))
+			.foreground(Color::RED))
+			.id(String::from("function error"))
+		)
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, 0.18f32), Vector2::new(0.96f32, 0.68f32)),
+				WidgetVariant::Scroll {offset: 0f32}
+			)
+			.id(String::from("function rows"))
+		)
+	}
+
+	fn build_default_triggers_panel() -> Widget<Message> {
+		Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.hidden()
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.2f32, -0.44f32), Vector2::new(0.5f32, 0.08f32)),
+				WidgetVariant::Label {text: String::from("Triggers"), font_size: 16i32}
+			)
+			.style(Style::default().background(Color::new(0, 0, 0, 0)))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.45f32, -0.44f32), Vector2::new(0.06f32, 0.08f32), "X", Message::CloseTriggers))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.12f32, -0.32f32), Vector2::new(0.7f32, 0.1f32)),
+				WidgetVariant::TextInput {
+					selected: false,
+					text: String::new(),
+					placeholder: String::from("when py > 400: vy = -0.8vy"),
+					cursor: 0u32,
+					registered: true
+				}
+			)
+			.id(String::from("trigger definition"))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.37f32, -0.32f32), Vector2::new(0.2f32, 0.1f32), "Add", Message::AddTrigger))
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(0f32, 0.14f32), Vector2::new(0.96f32, 0.76f32)),
+				WidgetVariant::Scroll {offset: 0f32}
+			)
+			.id(String::from("trigger rows"))
+		)
+	}
+
+	fn build_default_state_panel() -> Widget<Message> {
+		let input = |y: f32, placeholder: &str, id: &str| Widget::new(
+			Layout::new(Vector2::new(0f32, y), Vector2::new(0.9f32, 0.1f32)),
+			WidgetVariant::TextInput {
+				selected: false,
+				text: String::new(),
+				placeholder: String::from(placeholder),
+				cursor: 0u32,
+				registered: true
+			}
+		)
+		.id(String::from(id));
+
+		Widget::new(
+			Layout::default(),
+			WidgetVariant::Frame {outline_thickness: 1f32}
+		)
+		.style(Style::default()
+		.background(Color::BLACK)
+		.foreground(Color::new(255, 255, 255, 200)))
+		.hidden()
+		.add_child(
+			Widget::new(
+				Layout::new(Vector2::new(-0.2f32, -0.44f32), Vector2::new(0.5f32, 0.08f32)),
+				WidgetVariant::Label {text: String::from("State variables"), font_size: 16i32}
+			)
+			.style(Style::default().background(Color::new(0, 0, 0, 0)))
+		)
+		.add_child(Self::build_point_menu_button(Vector2::new(0.45f32, -0.44f32), Vector2::new(0.06f32, 0.08f32), "X", Message::CloseState))
+		.add_child(input(-0.3f32, "Initial: charge = 1; age = 0", "state initial"))
+		.add_child(input(-0.16f32, "Update: age = age + dt", "state update"))
+		.add_child(Self::build_point_menu_button(Vector2::new(0f32, -0.02f32), Vector2::new(0.3f32, 0.1f32), "Set", Message::SetStateVariables))
+		.add_child(input(0.2f32, "Selected point: charge = -1", "point state"))
+		.add_child(Self::build_point_menu_button(Vector2::new(0f32, 0.34f32), Vector2::new(0.3f32, 0.1f32), "Set on selected", Message::SetPointState))
+	}
+
+	fn default_camera() -> Camera2D {
+		Camera2D {
+			offset: Vector2::zero(),
+			target: Vector2::zero(),
+			rotation: 0f32,
+			zoom: 1f32
+		}
+	}
+
+	fn build_point_menu_label(y: f32, id: &str) -> Widget<Message> {
+		Widget::new(
+			Layout::new(Vector2::new(-0.25f32, y), Vector2::new(0.45f32, 0.1f32)),
+			WidgetVariant::Label {text: String::new(), font_size: 14i32}
+		)
+		.style(Style::default().background(Color::new(0, 0, 0, 0)))
+		.id(id.to_string())
+	}
+
+	fn build_point_menu_input(center: Vector2, placeholder: &str, id: &str) -> Widget<Message> {
+		Widget::new(
+			Layout::new(center, Vector2::new(0.22f32, 0.13f32)),
+			WidgetVariant::TextInput {
+				selected: false,
+				text: String::new(),
+				placeholder: placeholder.to_string(),
+				cursor: 0u32,
+				registered: true
+			}
+		)
+		.id(id.to_string())
+	}
+
+	fn build_point_menu_button(center: Vector2, size: Vector2, text: &str, message: Message) -> Widget<Message> {
+		Widget::new(
+			Layout::new(center, size),
+			WidgetVariant::Button {state: ButtonState::Rest}
+		)
+		.on_activation(message)
+		.add_child(
+			Widget::new(
+				Layout::default(),
+				WidgetVariant::Label {text: text.to_string(), font_size: 14i32}
+			).style(Style::default().background(Color::new(0, 0, 0, 0)))
+		)
+	}
+
+	pub fn mainloop(&mut self) {
+		while !self.rl_handle.window_should_close() {
+
+			if !self.paused {
+				// Speeds below 1 skip frames, speeds above 1 do several steps per frame
+				self.step_accumulator += self.sim_controls.root.get_value_in_tree("speed").unwrap_or(1f32);
+				// A trigger may pause the simulation in the middle of the frame
+				while self.step_accumulator >= 1f32 && !self.paused {
+					self.step();
+					self.step_accumulator -= 1f32;
+				}
+			}
+
+			self.update_sim_controls();
+			self.update_arrow_controls();
+			self.update_point_menu();
+			self.update_trail_menu();
+			self.update_plot_menu();
+			self.update_diagnostics_panel();
+			self.update_parameters();
+			self.handle_events();
+			self.draw();
+		}
+
+		println!("Application closed successfuly :)");
+	}
+
+	/// Advances the simulation by one time unit.
+	fn step(&mut self) {
+		self.measure_aggregates();
+		let mut events = Vec::<(Option::<usize>, Event)>::new();
+		for (i, p) in self.world.iter_mut().enumerate() {
+			// A point held by the user is moved by the mouse only
+			if Some(i) != self.dragged_point {
+				events.extend(p.simulate().into_iter().map(|e| (Some(i), e)));
+			}
+		}
+		let world_events = self.environment.borrow_mut().check_triggers();
+		events.extend(world_events.into_iter().map(|e| (None, e)));
+		self.apply_events(events);
+		self.environment.borrow_mut().time += 1f32;
+		self.sample_plot();
+		self.measure_diagnostics();
+	}
+
+	fn handle_events(&mut self) {
+
+		// Make widget trees hear events
+		self.inspector.check_event(&mut self.rl_handle);
+		self.force_inspector.check_event(&mut self.rl_handle);
+		self.force_menu.check_event(&mut self.rl_handle);
+		self.force_naming.check_event(&mut self.rl_handle);
+		self.point_menu.check_event(&mut self.rl_handle);
+		self.sim_controls.check_event(&mut self.rl_handle);
+		self.camera_controls.check_event(&mut self.rl_handle);
+		self.arrow_controls.check_event(&mut self.rl_handle);
+		self.trail_menu.check_event(&mut self.rl_handle);
+		self.plot_menu.check_event(&mut self.rl_handle);
+		self.diagnostics_panel.check_event(&mut self.rl_handle);
+		self.parameters_panel.check_event(&mut self.rl_handle);
+		self.functions_panel.check_event(&mut self.rl_handle);
+		self.state_panel.check_event(&mut self.rl_handle);
+		self.triggers_panel.check_event(&mut self.rl_handle);
+		self.contextual_menu.check_event(&mut self.rl_handle);
+
+		// Messages emitted by activated widgets
+		let mut messages = Vec::<Message>::new();
+		messages.append(&mut self.contextual_menu.poll_messages());
+		messages.append(&mut self.inspector.poll_messages());
+		messages.append(&mut self.force_inspector.poll_messages());
+		messages.append(&mut self.force_menu.poll_messages());
+		messages.append(&mut self.force_naming.poll_messages());
+		messages.append(&mut self.point_menu.poll_messages());
+		messages.append(&mut self.sim_controls.poll_messages());
+		messages.append(&mut self.camera_controls.poll_messages());
+		messages.append(&mut self.arrow_controls.poll_messages());
+		messages.append(&mut self.trail_menu.poll_messages());
+		messages.append(&mut self.plot_menu.poll_messages());
+		messages.append(&mut self.diagnostics_panel.poll_messages());
+		messages.append(&mut self.parameters_panel.poll_messages());
+		messages.append(&mut self.functions_panel.poll_messages());
+		messages.append(&mut self.state_panel.poll_messages());
+		messages.append(&mut self.triggers_panel.poll_messages());
+
+		// Keyboard shortcuts, ignored while the user is typing
+		if !self.is_typing() {
+			if self.rl_handle.is_key_pressed(KeyboardKey::KEY_SPACE) {
+				messages.push(Message::TogglePause);
+			}
+			if self.rl_handle.is_key_pressed(KeyboardKey::KEY_S) {
+				messages.push(Message::Step);
+			}
+			if self.rl_handle.is_key_pressed(KeyboardKey::KEY_F) {
+				messages.push(Message::ToggleFollow);
+			}
+			if self.rl_handle.is_key_pressed(KeyboardKey::KEY_A) {
+				messages.push(Message::FitAllPoints);
+			}
+			if self.rl_handle.is_key_pressed(KeyboardKey::KEY_G) {
+				messages.push(Message::ToggleGrid);
+			}
+		}
+
+		for message in messages {
+			self.dispatch(message);
+		}
+
+		// Special behaviours
+		self.camera_events();
+		self.world_events();
+		self.contextual_menu_events();
 		self.force_menu_events();
-		self.force_naming_events();
 				
 	}
 
-	fn draw(&mut self) {
-		let mut d = self.rl_handle.begin_drawing(&self.rl_thread);
+	fn dispatch(&mut self, message: Message) {
+		match message {
+			Message::AddPoint => {
+				self.add_point(self.mouse_world_position());
+				self.reset_drift();
+			},
+			Message::AddForce => {
+				self.force_naming.root.set_visible(true);
+				self.contextual_menu.root.set_visible(false);
+			},
+			Message::SelectPoint(index) => {
+				self.selected_point = Some(index);
+				self.point_menu.root.set_visible(true);
+			},
+			Message::TeleportPoint => {
+				if let (Some(i), Some(x), Some(y)) = (self.selected_point, self.read_point_menu_value("teleport x", Dimension::LENGTH), self.read_point_menu_value("teleport y", Dimension::LENGTH)) {
+					self.world[i].set_position(Vector2::new(x, y));
+					self.reset_drift();
+				}
+			},
+			Message::SetPointSpeed => {
+				if let (Some(i), Some(x), Some(y)) = (self.selected_point, self.read_point_menu_value("speed x", Dimension::LENGTH / Dimension::TIME), self.read_point_menu_value("speed y", Dimension::LENGTH / Dimension::TIME)) {
+					self.world[i].set_speed(Vector2::new(x, y));
+					self.reset_drift();
+				}
+			},
+			Message::SetPointMass => {
+				if let (Some(i), Some(m)) = (self.selected_point, self.read_point_menu_value("mass", Dimension::MASS)) {
+					if m > 0f32 {
+						self.world[i].set_mass(m);
+						self.reset_drift();
+					} else {
+						println!("Error: mass must be strictly positive.");
+					}
+				}
+			},
+			Message::TogglePointTrail => {
+				if let Some(i) = self.selected_point {
+					let visible = self.world[i].is_trail_visible();
+					self.world[i].set_trail_visibility(!visible);
+				}
+			},
+			Message::DeletePoint => {
+				if let Some(i) = self.selected_point {
+					self.world.remove(i);
+					self.selected_point = None;
+					self.dragged_point = None;
+					self.point_menu.root.set_visible(false);
+					self.refresh_point_list();
+					if let Some(traces) = &mut self.traces {
+						traces.invalidate();
+					}
+					self.clear_plot();
+					self.reset_drift();
+				}
+			},
+			Message::ClosePointMenu => {
+				self.selected_point = None;
+				self.point_menu.root.set_visible(false);
+			},
+			Message::EditForce(name) => self.show_force_menu(name),
+			Message::TogglePause => {
+				self.paused = !self.paused;
+				self.step_accumulator = 0f32;
+			},
+			Message::Step => {
+				self.paused = true;
+				self.step();
+			},
+			Message::ToggleFollow => self.follow_selected = !self.follow_selected,
+			Message::FitAllPoints => {
+				self.follow_selected = false;
+				self.fit_all_points();
+			},
+			Message::ResetCamera => {
+				self.follow_selected = false;
+				self.camera = Self::default_camera();
+			},
+			Message::ToggleGrid => self.grid.toggle(),
+			Message::ToggleSpeedArrows => self.arrows.speed = !self.arrows.speed,
+			Message::ToggleAccelerationArrows => self.arrows.acceleration = !self.arrows.acceleration,
+			Message::ToggleForceArrows => self.arrows.forces = !self.arrows.forces,
+			Message::ToggleArrowsOnAllPoints => self.arrows_on_all_points = !self.arrows_on_all_points,
+			Message::ApplyForce => self.apply_force(),
+			Message::PreviewField => {
+				if self.field.as_ref().is_some_and(|f| f.name() == self.selected_force) {
+					self.field = None;
+				} else {
+					match self.read_force_menu() {
+						Ok(force) => {
+							self.set_force_menu_error(String::new());
+							self.field = Some(ForceField::new(self.selected_force.clone(), force));
+							// Sums of the previewed force are measured with the others
+							self.measure_aggregates();
+						},
+						Err(e) => self.set_force_menu_error(e)
+					}
+				}
+			},
+			Message::CreateForce => self.create_force(),
+			Message::CancelForceCreation => self.force_naming.root.set_visible(false),
+			Message::ShowTrailMenu => {
+				self.trail_menu.root.set_visible(true);
+				self.contextual_menu.root.set_visible(false);
+			},
+			Message::CloseTrailMenu => self.trail_menu.root.set_visible(false),
+			Message::SetSelectedTrailLength => {
+				if let (Some(i), Some(length)) = (self.selected_point, self.read_trail_length()) {
+					self.world[i].set_trail_length(length);
+				}
+			},
+			Message::SetAllTrailLengths => {
+				if let Some(length) = self.read_trail_length() {
+					for p in self.world.iter_mut() {
+						p.set_trail_length(length);
+					}
+					self.default_trail_length = length;
+				}
+			},
+			Message::CycleTrailColoring => {
+				self.trail_options.coloring = match self.trail_options.coloring {
+					TrailColoring::Time => TrailColoring::Speed,
+					TrailColoring::Speed => TrailColoring::Point,
+					TrailColoring::Point => TrailColoring::Time
+				};
+			},
+			Message::ToggleTrailFade => self.trail_options.fade = !self.trail_options.fade,
+			Message::ShowPlotMenu => {
+				self.plot_menu.root.set_visible(true);
+				self.contextual_menu.root.set_visible(false);
+			},
+			Message::ClosePlotMenu => self.plot_menu.root.set_visible(false),
+			Message::SetPlotExpressions => self.set_plot_expressions(),
+			Message::ShowDiagnostics => {
+				self.diagnostics_panel.root.set_visible(true);
+				self.contextual_menu.root.set_visible(false);
+			},
+			Message::CloseDiagnostics => self.diagnostics_panel.root.set_visible(false),
+			Message::ResetDrift => self.reset_drift(),
+			Message::DeriveForceFromPotential => self.derive_force_from_potential(),
+			Message::ShowParameters => {
+				self.parameters_panel.root.set_visible(true);
+				self.contextual_menu.root.set_visible(false);
+			},
+			Message::CloseParameters => self.parameters_panel.root.set_visible(false),
+			Message::AddParameters => self.add_parameters(),
+			Message::RemoveParameter(name) => {
+				self.environment.borrow_mut().parameters.remove(&name);
+				self.build_parameter_rows();
+			},
+			Message::ShowFunctions => {
+				self.functions_panel.root.set_visible(true);
+				self.contextual_menu.root.set_visible(false);
+			},
+			Message::CloseFunctions => self.functions_panel.root.set_visible(false),
+			Message::AddFunction => self.add_function(),
+			Message::RemoveFunction(name) => match self.functions.remove(&name) {
+				Ok(()) => {
+					self.set_functions_error(String::new());
+					self.build_function_rows();
+				},
+				Err(e) => self.set_functions_error(format!("Error on function {name} : {e}"))
+			},
+			Message::ShowState => {
+				self.state_panel.root.set_visible(true);
+				self.contextual_menu.root.set_visible(false);
+			},
+			Message::CloseState => self.state_panel.root.set_visible(false),
+			Message::SetStateVariables => self.set_state_variables(),
+			Message::SetPointState => self.set_point_state(),
+			Message::ShowTriggers => {
+				self.triggers_panel.root.set_visible(true);
+				self.contextual_menu.root.set_visible(false);
+			},
+			Message::CloseTriggers => self.triggers_panel.root.set_visible(false),
+			Message::AddTrigger => self.add_trigger(),
+			Message::RemoveTrigger(source) => {
+				self.environment.borrow_mut().triggers.retain(|t| t.source != source);
+				self.build_trigger_rows();
+			}
+		}
+	}
+
+	fn draw(&mut self) {
+		let mouse_world = self.mouse_world_position();
+		let mut d = self.rl_handle.begin_drawing(&self.rl_thread);
+
+		d.clear_background(Color::WHITE);
+
+		self.grid.draw(&self.camera, &mut d);
+		if let Some(field) = &self.field {
+			field.draw(&self.camera, &self.environment.borrow(), &mut d);
+		}
+		if let Some(traces) = &mut self.traces {
+			traces.draw(&mut self.world, &self.camera, self.trail_options, &self.rl_thread, &mut d);
+		}
+
+		{
+			let mut world_drawing = d.begin_mode2D(self.camera);
+
+			for (i, point) in self.world.iter_mut().enumerate() {
+				point.draw(
+					if Some(i) == self.selected_point {
+						PointStyle::Cross
+					} else {
+						PointStyle::Circle
+					},
+					self.trail_options,
+					self.camera.zoom,
+					&mut world_drawing
+				);
+			}
+
+			for (i, point) in self.world.iter().enumerate() {
+				if self.arrows_on_all_points || Some(i) == self.selected_point {
+					point.draw_arrows(self.arrows, self.camera.zoom, &mut world_drawing);
+				}
+			}
+		}
+
+		self.inspector.draw(&mut d);
+		self.force_inspector.draw(&mut d);
+		self.force_menu.draw(&mut d);
+		self.force_naming.draw(&mut d);
+		self.point_menu.draw(&mut d);
+		self.sim_controls.draw(&mut d);
+		self.camera_controls.draw(&mut d);
+		self.arrow_controls.draw(&mut d);
+		self.trail_menu.draw(&mut d);
+		self.plot_menu.draw(&mut d);
+		self.diagnostics_panel.draw(&mut d);
+		self.parameters_panel.draw(&mut d);
+		self.functions_panel.draw(&mut d);
+		self.state_panel.draw(&mut d);
+		self.triggers_panel.draw(&mut d);
+		self.contextual_menu.draw(&mut d);
+
+		draw_cursor_readout(mouse_world, &mut d);
+	}
+
+
+	fn show_force_menu(&mut self, name: String) {
+		if let Some(title) = self.force_menu.seek("title") {
+			if let WidgetVariant::Label {text, ..} = title.get_variant() {
+				*text = name.clone();
+				self.selected_force = name.clone();
+			}
+		}
+
+		match self.forces.borrow().get(&name) {
+			// A force calling user functions or using vectors is shown as typed, as its program no longer reads like it
+			Some(Force {source: Some(source), ..}) => {
+				Self::fill_force_menu_input(&mut self.force_menu, "set ax", source.x.clone());
+				Self::fill_force_menu_input(&mut self.force_menu, "set ay", source.y.clone());
+				Self::fill_force_menu_input(&mut self.force_menu, "set u", source.potential.clone());
+			},
+			Some(f) => {
+				Self::fill_force_menu_program(&mut self.force_menu, &f.program);
+				let potential = f.potential.as_ref().map(|u| u.simplify().to_string()).unwrap_or_default();
+				Self::fill_force_menu_input(&mut self.force_menu, "set u", potential);
+			},
+			None => println!("Error: force {} doen't exist in model.", name)
+		}
+
+		self.set_force_menu_error(String::new());
+		self.force_menu.root.set_visible(true);
+		self.force_menu_just_appeared = true;
+	}
+
+	fn fill_force_menu_input(force_menu: &mut WidgetTree<Message>, id: &'static str, content: String) {
+		if let Some(input) = force_menu.seek(id) {
+			if let WidgetVariant::TextInput {text, cursor, ..} = input.get_variant() {
+				*text = content;
+				*cursor = text.len() as u32;
+			}
+		}
+	}
+
+	/// Shows a cleaned-up version of a force program in the X and Y inputs of the force menu.
+	/// A last `y` statement goes to the Y input and the others to the X input, the plain
+	/// expressions of single statements being shown without their name.
+	fn fill_force_menu_program(force_menu: &mut WidgetTree<Message>, program: &Program) {
+		let mut bindings = program.bindings.iter()
+			.map(|(name, expression)| (name.clone(), expression.simplify()))
+			.collect::<Vec::<(String, Expression)>>();
+
+		let y = match bindings.last() {
+			Some((name, _)) if name == "y" => bindings.pop().map(|(_, e)| e.to_string()).unwrap_or_default(),
+			_ => String::new()
+		};
+		let x = match bindings.as_slice() {
+			[(name, expression)] if name == "x" => expression.to_string(),
+			_ => Program {bindings}.to_string()
+		};
+
+		Self::fill_force_menu_input(force_menu, "set ax", x);
+		Self::fill_force_menu_input(force_menu, "set ay", y);
+	}
+
+	/// Replaces the force components typed in the force menu by minus the gradient of the typed potential.
+	fn derive_force_from_potential(&mut self) {
+		let potential = match Tokenizer::tokenize_with_functions(&self.force_menu.get_text("set u").unwrap_or_default(), &self.functions.names()) {
+			Ok(tokens) => tokens,
+			Err(e) => {
+				println!("Error on potential expression : {e}");
+				return;
+			}
+		};
+
+		match Force::from_potential(potential, &self.functions) {
+			Ok(force) => Self::fill_force_menu_program(&mut self.force_menu, &force.program),
+			Err(e) => println!("Error on potential expression : {e}")
+		}
+	}
+
+	fn add_point(&mut self, position: Vector2) {
+		// Adding point in world
+		let mut new_point = Point::new(
+			position,
+			Rc::clone(&self.forces),
+			Rc::clone(&self.environment)
+		);
+		new_point.set_trail_visibility(true);
+		new_point.set_trail_length(self.default_trail_length);
+		// Golden angle hue steps keep successive points colors apart
+		new_point.set_color(Color::color_from_hsv((self.world.len() as f32 * 137.5f32) % 360f32, 0.8f32, 0.8f32));
+		/*
+		for f in self.forces.borrow().iter() {
+			if let Err(e) = new_point.add_force(Rc::clone(&f.1)) {
+				println!("Error while adding force on newly created point : {e}.");
+			}
+		}
+		*/
+		self.world.push(new_point);
+
+		// Adding point handle in inspector
+		let index = self.world.len() - 1;
+		match self.inspector.seek("point scroll") {
+			Some(s) => {
+				Self::add_button_to_scroll(s, format!("point{index}"), Message::SelectPoint(index));
+			},
+			None => println!("Error: No scroll menu in inspector, what happened ?")
+		}
+	}
+
+	/// Rebuilds the point buttons of the inspector, needed when points indices change.
+	fn refresh_point_list(&mut self) {
+		match self.inspector.seek("point scroll") {
+			Some(s) => {
+				s.clear_children();
+				for index in 0..self.world.len() {
+					Self::add_button_to_scroll(s, format!("point{index}"), Message::SelectPoint(index));
+				}
+			},
+			None => println!("Error: No scroll menu in inspector, what happened ?")
+		}
+	}
+
+	/// Refreshes the live data shown in the point menu.
+	fn update_point_menu(&mut self) {
+		let point = match self.selected_point {
+			Some(i) => &self.world[i],
+			None => return
+		};
+
+		let forces = point.force_contributions()
+			.iter()
+			.map(|(name, f)| format!("{name} ({:.2}, {:.2})", f.x, f.y))
+			.collect::<Vec::<String>>()
+			.join(", ");
+		let mut state = point.state().iter()
+			.map(|(name, v)| format!("{name} = {v:.2}"))
+			.collect::<Vec::<String>>();
+		state.sort();
+		let state = state.join(", ");
+
+		let texts = [
+			("point title", format!("point{}", self.selected_point.unwrap())),
+			("point position", format!("p = ({:.1}, {:.1})", point.position().x, point.position().y)),
+			("point speed", format!("v = ({:.2}, {:.2})", point.speed().x, point.speed().y)),
+			("point acceleration", format!("a = ({:.3}, {:.3})", point.acceleration().x, point.acceleration().y)),
+			("point mass", format!("m = {}  trail: {}", point.mass(), match point.trail_length() {
+				Some(l) => l.to_string(),
+				None => String::from("persistent")
+			})),
+			("point forces", if forces.is_empty() {String::from("No force")} else {forces}),
+			("point state", if state.is_empty() {String::from("No state")} else {state})
+		];
+
+		for (id, t) in texts {
+			if let Some(w) = self.point_menu.seek(id) {
+				if let WidgetVariant::Label {text, ..} = w.get_variant() {
+					*text = t;
+				}
+			}
+		}
+	}
+
+	/// Evaluates a point menu input in the context of the selected point.
+	/// Inputs may be expressions, for instance "px + 100", whose dimension must match `expected`.
+	fn read_point_menu_value(&self, id: &'static str, expected: Dimension) -> Option<f32> {
+		let context = self.world[self.selected_point?].context();
+		let entry = self.point_menu.get_entry(id).unwrap_or("0".to_string());
+
+		match self.compile_expression(&entry).map(|e| (e.check_dimension(expected), e.evaluate(&context))) {
+			Ok((Ok(()), Ok(v))) => Some(v),
+			Ok((Err(e), _)) => {
+				println!("Error on {id} : {e}.");
+				None
+			},
+			Ok((_, Err(e))) => {
+				println!("Error on {id} : {e}");
+				None
+			},
+			Err(e) => {
+				println!("Error on {id} : {e}");
+				None
+			}
+		}
+	}
+
+	/// Evaluates the trail length typed in the trail menu. Lengths below 1 stand for persistent
+	/// trails, hence `Some(None)`, while `None` means the input is invalid.
+	fn read_trail_length(&self) -> Option<Option<usize>> {
+		let entry = self.trail_menu.get_entry("trail length").unwrap_or("0".to_string());
+		if entry.trim().is_empty() {
+			return Some(None);
+		}
+
+		match self.compile_expression(&entry) {
+			Ok(expression) => match expression.evaluate(&VariableContext::new()) {
+				Ok(v) if v >= 1f32 => Some(Some(v.round() as usize)),
+				Ok(_) => Some(None),
+				Err(e) => {
+					println!("Error on trail length : {e}");
+					None
+				}
+			},
+			Err(e) => {
+				println!("Error on trail length : {e}");
+				None
+			}
+		}
+	}
+
+	/// Reads the plot menu inputs and starts plotting them. Each `;` separated Y expression
+	/// gets its own series. Expressions are checked on a probe so that typos are reported once.
+	fn set_plot_expressions(&mut self) {
+		// Raw texts, as empty inputs have a meaning here
+		let x_entry = self.plot_menu.get_text("plot x").unwrap_or_default();
+		let y_entry = self.plot_menu.get_text("plot y").unwrap_or_default();
+		let probe = Point::probe_context(Vector2::zero(), &self.environment.borrow());
 
-		d.clear_background(Color::WHITE);
+		let check = |expression: &str| match self.compile_expression(expression) {
+			Ok(compiled) => match compiled.evaluate(&probe) {
+				Ok(_) => Some(compiled),
+				Err(e) => {
+					println!("Error on plotted expression {expression} : {e}");
+					None
+				}
+			},
+			Err(e) => {
+				println!("Error on plotted expression {expression} : {e}");
+				None
+			}
+		};
 
-		for (i, point) in self.world.iter_mut().enumerate() {
-			point.draw(
-				if i == self.selected_point as usize {
-					PointStyle::Cross
-				} else {
-					PointStyle::Circle
-				},
-				&mut d
-			);
+		let x = if x_entry.trim().is_empty() {None} else {
+			match check(&x_entry) {
+				Some(compiled) => Some(compiled),
+				None => return
+			}
+		};
+		let labels = y_entry.split(';')
+			.map(|e| e.trim().to_string())
+			.filter(|e| !e.is_empty())
+			.collect::<Vec::<String>>();
+		let mut series = Vec::<Expression>::new();
+		for label in labels.iter() {
+			match check(label) {
+				Some(compiled) => series.push(compiled),
+				None => return
+			}
 		}
 
-		self.inspector.draw(&mut d);
-		self.force_inspector.draw(&mut d);
-		self.force_menu.draw(&mut d);
-		self.force_naming.draw(&mut d);
-		self.contextual_menu.draw(&mut d);
+		self.plot_x = x;
+		self.plot_series = series;
+		if let Some(plot) = self.plot_menu.seek("plot") {
+			if let WidgetVariant::Plot {series, ..} = plot.get_variant() {
+				*series = labels.into_iter()
+					.enumerate()
+					// Golden angle hue steps keep neighbouring series colors apart
+					.map(|(i, label)| PlotSeries::new(label, Color::color_from_hsv((i as f32 * 137.5f32) % 360f32, 0.8f32, 0.7f32)))
+					.collect();
+			}
+		}
+		self.clear_plot();
+	}
+
+	/// Forgets every plotted sample, e.g. when the plotted point changes.
+	fn clear_plot(&mut self) {
+		self.plotted_point = self.selected_point;
+		if let Some(plot) = self.plot_menu.seek("plot") {
+			if let WidgetVariant::Plot {series, ..} = plot.get_variant() {
+				for s in series.iter_mut() {
+					s.samples.clear();
+				}
+			}
+		}
 	}
 
+	/// Adds the current value of the plotted expressions on the selected point to the plot.
+	/// Samples whose evaluation fails are skipped.
+	fn sample_plot(&mut self) {
+		if self.plot_series.is_empty() {
+			return;
+		}
+		if self.plotted_point != self.selected_point {
+			self.clear_plot();
+		}
+		let Some(i) = self.selected_point else {return;};
 
-	fn show_force_menu(&mut self, name: String) {
-		if let Some(title) = self.force_menu.seek("title") {
-			if let WidgetVariant::Label {text, ..} = title.get_variant() {
-				*text = name.clone();
-				self.selected_force = name.clone();
+		let context = self.world[i].context();
+		let x = match &self.plot_x {
+			Some(expression) => match expression.evaluate(&context) {
+				Ok(v) => v,
+				Err(_) => return
+			},
+			None => self.environment.borrow().time
+		};
+		let samples = self.plot_series.iter()
+			.map(|expression| expression.evaluate(&context))
+			.collect::<Vec::<_>>();
+
+		if let Some(plot) = self.plot_menu.seek("plot") {
+			for (index, sample) in samples.into_iter().enumerate() {
+				if let Ok(y) = sample {
+					plot.push_plot_sample(index, Vector2::new(x, y));
+				}
 			}
 		}
+	}
 
-		match self.forces.borrow().get(&name) {
-			Some(f) => {
-				if let Some(ax) = self.force_menu.seek("set ax") {
-					if let WidgetVariant::TextInput {text, cursor, ..} = ax.get_variant() {
-						*text = Tokenizer::untokenize(&f.x);
-						*cursor = text.len() as u32;
-					}
+	/// Applies the window slider of the plot menu.
+	fn update_plot_menu(&mut self) {
+		let length = self.plot_menu.root.get_value_in_tree("plot window").unwrap_or(500f32);
+		if let Some(plot) = self.plot_menu.seek("plot") {
+			if let WidgetVariant::Plot {window, ..} = plot.get_variant() {
+				*window = length as usize;
+			}
+		}
+	}
+
+	/// Measures the world diagnostics, the first measure after an edit becoming the drift reference.
+	fn measure_diagnostics(&mut self) {
+		let diagnostics = Diagnostics::measure(&self.world);
+		self.diagnostics = Some(diagnostics);
+		if self.diagnostics_reference.is_none() {
+			self.diagnostics_reference = Some(diagnostics);
+		}
+	}
+
+	/// Measures the drift of the diagnostics from the current state of the world, after an edit.
+	/// Aggregates are measured again so that the edit is seen without waiting for the next step.
+	fn reset_drift(&mut self) {
+		self.measure_aggregates();
+		self.diagnostics_reference = None;
+		self.measure_diagnostics();
+	}
+
+	/// Shows the last diagnostics and their drift since the reference in the diagnostics panel.
+	fn update_diagnostics_panel(&mut self) {
+		let (Some(d), Some(r)) = (self.diagnostics, self.diagnostics_reference) else {return;};
+
+		let drift = |value: f32, reference: f32| {
+			if reference.abs() > 1e-6f32 {
+				format!("drift {:+.3}, {:+.2}%", value - reference, 100f32 * (value - reference) / reference.abs())
+			} else {
+				format!("drift {:+.3}", value - reference)
+			}
+		};
+
+		let texts = [
+			("kinetic energy", format!("Ek = {:.3} ({})", d.kinetic_energy, drift(d.kinetic_energy, r.kinetic_energy))),
+			("potential energy", format!(
+				"Ep = {:.3} ({}){}",
+				d.potential_energy,
+				drift(d.potential_energy, r.potential_energy),
+				if d.potential_complete {""} else {" partial"}
+			)),
+			("total energy", format!("E = {:.3} ({})", d.total_energy(), drift(d.total_energy(), r.total_energy()))),
+			("momentum", format!(
+				"p = ({:.3}, {:.3}) (drift {:+.3}, {:+.3})",
+				d.momentum.x, d.momentum.y, d.momentum.x - r.momentum.x, d.momentum.y - r.momentum.y
+			)),
+			("angular momentum", format!("L = {:.3} ({})", d.angular_momentum, drift(d.angular_momentum, r.angular_momentum)))
+		];
+
+		for (id, t) in texts {
+			if let Some(w) = self.diagnostics_panel.seek(id) {
+				if let WidgetVariant::Label {text, ..} = w.get_variant() {
+					*text = t;
 				}
-				if let Some(ay) = self.force_menu.seek("set ay") {
-					if let WidgetVariant::TextInput {text, cursor, ..} = ay.get_variant() {
-						*text = Tokenizer::untokenize(&f.y);
-						*cursor = text.len() as u32;
+			}
+		}
+	}
+
+	/// Reads the force typed in the force menu, whose X and Y inputs may hold scripts sharing their
+	/// bindings. An empty potential means the force has none. Its expressions must be dimensionally
+	/// consistent, otherwise the error explains what is wrong, while components which aren't forces
+	/// only print a warning.
+	fn read_force_menu(&self) -> Result<Force, String> {
+		// Raw texts, as an empty input adds no statement to the script
+		let source = ForceSource {
+			x: self.force_menu.get_text("set ax").unwrap_or_default(),
+			y: self.force_menu.get_text("set ay").unwrap_or_default(),
+			potential: self.force_menu.get_text("set u").unwrap_or_default()
+		};
+		let functions = self.functions.names();
+		let tokenize = |label: &str, text: &str| Tokenizer::tokenize_with_functions(text, &functions)
+			.map_err(|e| format!("Error on {label} expression : {e}"));
+		let (x, y, potential) = (tokenize("X", &source.x)?, tokenize("Y", &source.y)?, tokenize("potential", &source.potential)?);
+
+		let force = Force::from_scripts(&x, &y, potential, &self.functions)
+			.map_err(|e| format!("Error on force script : {e}"))?
+			.written_as(source);
+		for warning in force.check_dimensions().map_err(|e| format!("Error on force script : {e}"))? {
+			println!("Warning on force : {warning}");
+		}
+		if let Some(Err(e)) = force.potential.as_ref().map(|u| u.dimension()) {
+			return Err(format!("Error on potential expression : {e}"));
+		}
+
+		Ok(force)
+	}
+
+	/// Shows `message` under the force menu inputs, an empty message hiding the previous one.
+	fn set_force_menu_error(&mut self, message: String) {
+		if let Some(w) = self.force_menu.seek("force error") {
+			if let WidgetVariant::Label {text, ..} = w.get_variant() {
+				*text = message;
+			}
+		}
+	}
+
+	fn apply_force(&mut self) {
+		match self.read_force_menu() {
+			Ok(force) => {
+				self.forces.borrow_mut().insert(self.selected_force.clone(), force);
+				self.selected_force.clear();
+				self.reset_drift();
+				self.set_force_menu_error(String::new());
+				self.force_menu.root.set_visible(false);
+			},
+			Err(e) => self.set_force_menu_error(e)
+		}
+	}
+
+	fn create_force(&mut self) {
+		match self.force_inspector.seek("force scroll") {
+			Some (s) => {
+				let name = match self.force_naming.seek("name") {
+					Some(n) => {
+						if let WidgetVariant::TextInput{text, cursor, ..} = n.get_variant() {
+							let t = text.clone();
+							text.clear();
+							*cursor = 0u32;
+							t
+						} else {
+							println!("Error: Widget of ID 'name' is not a TextInput.");
+							String::from("Unknown")
+						}
+					},
+					None => String::from("Unknown")
+				};
+				self.forces.borrow_mut().insert(name.clone(), Force::new());
+				Self::add_button_to_scroll(s, name.clone(), Message::EditForce(name));
+				self.force_naming.root.set_visible(false);
+			},
+			None => println!("Error: No scroll menu in force inspector, what happened ?")
+		}
+	}
+
+	/// Compiles the statements typed in an input, such as "k = 0.5; c = 2k": calls to user
+	/// functions are inlined and vectors expanded into their components.
+	fn compile_program(&self, text: &str) -> Result<Program, String> {
+		Tokenizer::tokenize_with_functions(text, &self.functions.names())
+			.map_err(|e| format!("{e}"))
+			.and_then(|tokens| Program::build(&tokens, "").map_err(|e| format!("{e}")))
+			.and_then(|program| self.functions.inline_program(&program).map_err(|e| format!("{e}")))
+			.and_then(|program| expand_program(&program, &[]).map_err(|e| e.to_string()))
+	}
+
+	/// Compiles the expression typed in an input like `compile_program`, its result being a scalar.
+	fn compile_expression(&self, text: &str) -> Result<Expression, String> {
+		Tokenizer::tokenize_with_functions(text, &self.functions.names())
+			.map_err(|e| format!("{e}"))
+			.and_then(|tokens| Parser::build(&tokens).map_err(|e| format!("{e}")))
+			.and_then(|expression| self.functions.inline(&expression).map_err(|e| format!("{e}")))
+			.and_then(|expression| expand_scalar(&expression).map_err(|e| e.to_string()))
+	}
+
+	/// Reads the state variables typed in the state panel: the initial statements bind the
+	/// variables, and the update statements, which may only bind them, run on every point after
+	/// each step. Existing points give their initial value to the variables they don't carry yet.
+	fn set_state_variables(&mut self) {
+		let build = |label: &str, id: &'static str| self.compile_program(&self.state_panel.get_text(id).unwrap_or_default())
+			.map_err(|e| format!("Error on {label} state : {e}"));
+
+		let state = match (build("initial", "state initial"), build("update", "state update")) {
+			(Ok(initial), Ok(update)) => StateVariables {initial, update},
+			(Err(e), _) | (_, Err(e)) => {
+				println!("{e}");
+				return;
+			}
+		};
+		if let Err(e) = state.check() {
+			println!("Error on state : {e}.");
+			return;
+		}
+
+		self.environment.borrow_mut().state = state;
+		for p in self.world.iter_mut() {
+			p.initialize_state();
+		}
+	}
+
+	/// Sets state variables of the selected point, typed as statements such as "charge = -1"
+	/// evaluated on the point.
+	fn set_point_state(&mut self) {
+		let Some(i) = self.selected_point else {return;};
+		let entry = self.state_panel.get_text("point state").unwrap_or_default();
+		let program = match self.compile_program(&entry) {
+			Ok(program) => program,
+			Err(e) => {
+				println!("Error on point state : {e}");
+				return;
+			}
+		};
+
+		let point = &mut self.world[i];
+		match program.evaluate(&point.context()) {
+			Ok(values) => {
+				for (name, _) in program.bindings.iter() {
+					if let Err(e) = point.set_state(name, values[name]) {
+						println!("Error on point state : {e}.");
 					}
 				}
 			},
-			None => println!("Error: force {} doen't exist in model.", name)
+			Err(e) => println!("Error on point state : {e}")
 		}
-
-		self.force_menu.root.set_visible(true);
-		self.force_menu_just_appeared = true;
 	}
 
-	fn contextual_menu_events(&mut self) {
-		
-		let contextual_activations = self.contextual_menu.root.get_all_activations();
+	/// Adds the trigger typed in the triggers panel, e.g. "when py > 400: vy = -0.8vy".
+	fn add_trigger(&mut self) {
+		let entry = self.triggers_panel.get_text("trigger definition").unwrap_or_default();
+		let trigger = match Trigger::build(&entry, &self.functions) {
+			Ok(trigger) => trigger,
+			Err(e) => {
+				println!("Error on trigger : {e}.");
+				return;
+			}
+		};
+		if self.environment.borrow().triggers.iter().any(|t| t.source == trigger.source) {
+			println!("Error on trigger : \"{}\" already exists.", trigger.source);
+			return;
+		}
 
-		if contextual_activations.contains(&String::from("add force")) {
-			self.force_naming.root.set_visible(true);
-			self.contextual_menu.root.set_visible(false);
+		self.environment.borrow_mut().triggers.push(trigger);
+		if let Some(input) = self.triggers_panel.seek("trigger definition") {
+			if let WidgetVariant::TextInput {text, cursor, ..} = input.get_variant() {
+				text.clear();
+				*cursor = 0u32;
+			}
 		}
-		if contextual_activations.contains(&String::from("add point")) {
-			
-			// Adding point in world
-			let mut new_point = Point::new(
-				Vector2::new(
-					self.rl_handle.get_mouse_position().x,
-					self.rl_handle.get_mouse_position().y
-				),
-				Rc::clone(&self.forces)
+		self.build_trigger_rows();
+	}
+
+	/// Rebuilds the rows of the triggers panel, one trigger and remove button per trigger.
+	fn build_trigger_rows(&mut self) {
+		let sources = self.environment.borrow().triggers.iter()
+			.map(|t| t.source.clone())
+			.collect::<Vec::<String>>();
+
+		let Some(rows) = self.triggers_panel.seek("trigger rows") else {
+			println!("Error: No trigger rows in triggers panel, what happened ?");
+			return;
+		};
+		rows.clear_children();
+
+		for (i, source) in sources.into_iter().enumerate() {
+			let y = -0.42f32 + 0.14f32 * i as f32;
+			rows.add_child_inplace(
+				Widget::new(
+					Layout::new(Vector2::new(-0.05f32, y), Vector2::new(0.88f32, 0.11f32)),
+					WidgetVariant::Label {text: source.clone(), font_size: 14i32}
+				)
+				.style(Style::default().background(Color::new(0, 0, 0, 0)))
 			);
-			new_point.set_trail_visibility(true);
-			/*
-			for f in self.forces.borrow().iter() {
-				if let Err(e) = new_point.add_force(Rc::clone(&f.1)) {
-					println!("Error while adding force on newly created point : {e}.");
+			rows.add_child_inplace(Self::build_point_menu_button(Vector2::new(0.45f32, y), Vector2::new(0.06f32, 0.11f32), "-", Message::RemoveTrigger(source)));
+		}
+	}
+
+	/// Applies the events of the triggers fired during a step, each coming from the point of
+	/// the given index or from the world.
+	fn apply_events(&mut self, events: Vec::<(Option::<usize>, Event)>) {
+		let time = self.environment.borrow().time;
+		let mut spawned = false;
+		for (i, event) in events {
+			match event {
+				Event::Pause => self.paused = true,
+				Event::Log(source) => match i {
+					Some(i) => println!("t={time} point{i} : {source}"),
+					None => println!("t={time} : {source}")
+				},
+				Event::Spawn(position, speed) => {
+					self.add_point(position);
+					if let Some(p) = self.world.last_mut() {
+						p.set_speed(speed);
+					}
+					spawned = true;
 				}
 			}
-			*/
-			self.world.push(new_point);
+		}
+		if spawned {
+			self.reset_drift();
+		}
+	}
 
-			// Adding point handle in inspector
-			match self.inspector.seek("point scroll") {
-				Some(s) => {
-					Self::add_button_to_scroll(s, |x: u32| format!("point{x}"));
-				},
-				None => println!("Error: No scroll menu in inspector, what happened ?")
+	/// Measures the collective quantities of the world, with the sums used by the applied forces
+	/// and the previewed one.
+	fn measure_aggregates(&mut self) {
+		let mut sums = self.forces.borrow().values()
+			.flat_map(|f| f.sums())
+			.collect::<Vec::<Expression>>();
+		if let Some(field) = &self.field {
+			sums.append(&mut field.force().sums());
+		}
+		for trigger in self.environment.borrow().triggers.iter() {
+			sums.append(&mut trigger.sums());
+		}
+		for expression in self.plot_x.iter().chain(self.plot_series.iter()) {
+			sums.append(&mut expression.calls("sum"));
+		}
+
+		let aggregates = Aggregates::measure(&self.world, &sums);
+		self.environment.borrow_mut().aggregates = aggregates;
+	}
+
+	/// Defines or redefines the parameters typed in the parameters panel, as a script such as
+	/// "k = 0.5; c = 2k" whose statements may use the parameters defined before them.
+	fn add_parameters(&mut self) {
+		let entry = self.parameters_panel.get_text("parameter definition").unwrap_or_default();
+		match self.compile_program(&entry) {
+			Ok(program) => {
+				for (name, _) in program.bindings.iter() {
+					// Parameters can't hide the variables of points, of plots or the outputs of force scripts
+					if name.is_empty() || Point::is_reserved(name) {
+						println!("Error on parameters : \"{name}\" can't be used as a parameter name.");
+						return;
+					}
+				}
+
+				let values = program.evaluate(&self.environment.borrow().parameters);
+				match values {
+					Ok(values) => self.environment.borrow_mut().parameters = values,
+					Err(e) => {
+						println!("Error on parameters : {e}");
+						return;
+					}
+				}
+			},
+			Err(e) => {
+				println!("Error on parameters : {e}");
+				return;
 			}
-			
 		}
 
-		if self.rl_handle.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
-			self.contextual_menu.bounds.center = self.rl_handle.get_mouse_position() + self.contextual_menu.bounds.size/2f32;
-			self.contextual_menu.root.set_visible(true);
+		if let Some(input) = self.parameters_panel.seek("parameter definition") {
+			if let WidgetVariant::TextInput {text, cursor, ..} = input.get_variant() {
+				text.clear();
+				*cursor = 0u32;
+			}
 		}
-		if self.rl_handle.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && !self.contextual_menu.bounds.contains(self.rl_handle.get_mouse_position()) {
-			self.contextual_menu.root.set_visible(false);
+		self.build_parameter_rows();
+	}
+
+	/// Range of the slider of a parameter of value `value`, from 0 to twice the value.
+	fn parameter_range(value: f32) -> (f32, f32) {
+		if value == 0f32 {
+			(-1f32, 1f32)
+		} else {
+			((2f32 * value).min(0f32), (2f32 * value).max(0f32))
 		}
 	}
 
-	fn force_inspector_events(&mut self) {
-		let activations = self.force_inspector.root.get_all_activations();
-		
-		for id in activations.iter() {
-			self.show_force_menu(id.clone());
+	/// Rebuilds the rows of the parameters panel: name, slider, value and remove button, sorted by name.
+	fn build_parameter_rows(&mut self) {
+		let mut parameters = self.environment.borrow().parameters.iter()
+			.map(|(name, value)| (name.clone(), *value))
+			.collect::<Vec::<(String, f32)>>();
+		parameters.sort_by(|a, b| a.0.cmp(&b.0));
+
+		let Some(rows) = self.parameters_panel.seek("parameter rows") else {
+			println!("Error: No parameter rows in parameters panel, what happened ?");
+			return;
+		};
+		rows.clear_children();
+
+		for (i, (name, value)) in parameters.into_iter().enumerate() {
+			let y = -0.42f32 + 0.14f32 * i as f32;
+			let (min, max) = Self::parameter_range(value);
+
+			rows.add_child_inplace(
+				Widget::new(
+					Layout::new(Vector2::new(-0.36f32, y), Vector2::new(0.22f32, 0.11f32)),
+					WidgetVariant::Label {text: name.clone(), font_size: 14i32}
+				)
+				.style(Style::default().background(Color::new(0, 0, 0, 0)))
+			);
+			rows.add_child_inplace(
+				Widget::new(
+					Layout::new(Vector2::new(0f32, y), Vector2::new(0.44f32, 0.06f32)),
+					WidgetVariant::Slider {value, min, max, grabbed: false}
+				)
+				.style(Style::default().action(Color::GRAY))
+				.id(format!("parameter slider {name}"))
+			);
+			rows.add_child_inplace(
+				Widget::new(
+					Layout::new(Vector2::new(0.32f32, y), Vector2::new(0.18f32, 0.11f32)),
+					WidgetVariant::TextInput {
+						selected: false,
+						text: format!("{value}"),
+						placeholder: String::new(),
+						cursor: 0u32,
+						registered: true
+					}
+				)
+				.id(format!("parameter value {name}"))
+			);
+			rows.add_child_inplace(Self::build_point_menu_button(Vector2::new(0.45f32, y), Vector2::new(0.06f32, 0.11f32), "-", Message::RemoveParameter(name)));
 		}
 	}
 
-	fn inspector_events(&mut self) {
-		let inspector_activations = self.inspector.root.get_all_activations();
+	/// Applies the parameters panel to the parameters, which points read at each step: a moved
+	/// slider sets its parameter and a validated value field sets both the parameter and the
+	/// slider range. A validated seed replaces the seed of the random numbers.
+	fn update_parameters(&mut self) {
+		if let Some(entry) = self.parameters_panel.root.check_entry_in_tree("random seed") {
+			match entry.trim().parse::<u64>() {
+				Ok(seed) => self.environment.borrow_mut().seed = seed,
+				Err(e) => println!("Error on seed : {e}")
+			}
+		}
+
+		let mut parameters = self.environment.borrow().parameters.iter()
+			.map(|(name, value)| (name.clone(), *value))
+			.collect::<Vec::<(String, f32)>>();
+
+		for (name, value) in parameters.iter_mut() {
+			let field_id = format!("parameter value {name}");
+			let slider_id = format!("parameter slider {name}");
 
-		for id in inspector_activations.iter() {
-			if id.starts_with("point") {
-				match id[5..].parse::<i32>() {
-					Ok(v) =>  {
-						self.selected_point = v;
-						// TODO: Show point menu
+			if let Some(entry) = self.parameters_panel.root.check_entry_in_tree(&field_id) {
+				match self.compile_expression(&entry).map(|e| e.evaluate(&self.environment.borrow().parameters)) {
+					Ok(Ok(v)) => {
+						*value = v;
+						if let Some(slider) = self.parameters_panel.seek(&slider_id) {
+							if let WidgetVariant::Slider {value, min, max, ..} = slider.get_variant() {
+								(*min, *max) = Self::parameter_range(v);
+								*value = v;
+							}
+						}
+					},
+					Ok(Err(e)) => println!("Error on parameter {name} : {e}"),
+					Err(e) => println!("Error on parameter {name} : {e}")
+				}
+			} else if let Some(v) = self.parameters_panel.root.get_value_in_tree(&slider_id) {
+				if v != *value {
+					*value = v;
+					if let Some(field) = self.parameters_panel.seek(&field_id) {
+						if let WidgetVariant::TextInput {text, cursor, selected: false, ..} = field.get_variant() {
+							*text = format!("{v:.3}");
+							*cursor = text.len() as u32;
+						}
 					}
-					Err(_) => println!("Ill formated point name, expected i32 after column 5.")
 				}
 			}
 		}
+
+		self.environment.borrow_mut().parameters.extend(parameters);
 	}
 
-	fn force_menu_events(&mut self) {
+	/// Defines the function typed in the functions panel, e.g. "spring(d, k) = -k*d". Its body
+	/// may call the functions already defined, and itself so that recursion is reported.
+	/// Errors are shown in the panel.
+	fn add_function(&mut self) {
+		match self.define_function() {
+			Ok(()) => {
+				if let Some(input) = self.functions_panel.seek("function definition") {
+					if let WidgetVariant::TextInput {text, cursor, ..} = input.get_variant() {
+						text.clear();
+						*cursor = 0u32;
+					}
+				}
+				self.set_functions_error(String::new());
+				self.build_function_rows();
+			},
+			Err(e) => self.set_functions_error(e)
+		}
+	}
+
+	fn define_function(&mut self) -> Result<(), String> {
+		let entry = self.functions_panel.get_text("function definition").unwrap_or_default();
+		let is_name = |s: &str| s.starts_with(|c: char| c.is_ascii_alphabetic()) && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+		let syntax = || "Error on function : expected \"name(parameters) = expression\"".to_string();
+
+		let (head, body) = entry.split_once('=').ok_or_else(syntax)?;
+		let (name, parameters) = head.trim().strip_suffix(')').and_then(|h| h.split_once('(')).ok_or_else(syntax)?;
+		let name = name.trim();
+		let parameters = parameters.split(',')
+			.map(|p| p.trim().to_string())
+			.filter(|p| !p.is_empty())
+			.collect::<Vec::<String>>();
+
+		if !is_name(name) || Point::VARIABLES.contains(&name) {
+			return Err(format!("Error on function : \"{name}\" can't be used as a function name"));
+		}
+		if let Some(p) = parameters.iter().enumerate().find(|(i, p)| !is_name(p) || parameters[..*i].contains(p)).map(|(_, p)| p) {
+			return Err(format!("Error on function {name} : invalid or repeated parameter \"{p}\""));
+		}
+
+		let mut functions = self.functions.names();
+		functions.push(name);
+		let tokens = Tokenizer::tokenize_with_functions(body, &functions).map_err(|e| format!("Error on function {name} : {e}"))?;
+		Parser::build(&tokens)
+			.and_then(|body| self.functions.define(name, parameters, body))
+			.map_err(|e| format!("Error on function {name} : {e}"))
+	}
+
+	/// Shows `message` under the input of the functions panel, an empty message hiding the previous one.
+	fn set_functions_error(&mut self, message: String) {
+		if let Some(w) = self.functions_panel.seek("function error") {
+			if let WidgetVariant::Label {text, ..} = w.get_variant() {
+				*text = message;
+			}
+		}
+	}
+
+	/// Rebuilds the rows of the functions panel, one definition and remove button per function.
+	fn build_function_rows(&mut self) {
+		let definitions = self.functions.names().into_iter()
+			.filter_map(|name| self.functions.get(name).map(|f| (name.to_string(), format!("{name}({}) = {}", f.parameters.join(", "), f.body))))
+			.collect::<Vec::<(String, String)>>();
+
+		let Some(rows) = self.functions_panel.seek("function rows") else {
+			println!("Error: No function rows in functions panel, what happened ?");
+			return;
+		};
+		rows.clear_children();
+
+		for (i, (name, definition)) in definitions.into_iter().enumerate() {
+			let y = -0.42f32 + 0.14f32 * i as f32;
+			rows.add_child_inplace(
+				Widget::new(
+					Layout::new(Vector2::new(-0.05f32, y), Vector2::new(0.88f32, 0.11f32)),
+					WidgetVariant::Label {text: definition, font_size: 14i32}
+				)
+				.style(Style::default().background(Color::new(0, 0, 0, 0)))
+			);
+			rows.add_child_inplace(Self::build_point_menu_button(Vector2::new(0.45f32, y), Vector2::new(0.06f32, 0.11f32), "-", Message::RemoveFunction(name)));
+		}
+	}
+
+	fn is_mouse_over_ui(&self) -> bool {
+		let mouse = self.rl_handle.get_mouse_position();
+
+		self.inspector.contains(mouse) ||
+		self.force_menu.contains(mouse) ||
+		self.contextual_menu.contains(mouse) ||
+		self.force_inspector.contains(mouse) ||
+		self.force_naming.contains(mouse) ||
+		self.point_menu.contains(mouse) ||
+		self.sim_controls.contains(mouse) ||
+		self.camera_controls.contains(mouse) ||
+		self.arrow_controls.contains(mouse) ||
+		self.trail_menu.contains(mouse) ||
+		self.plot_menu.contains(mouse) ||
+		self.diagnostics_panel.contains(mouse) ||
+		self.parameters_panel.contains(mouse) ||
+		self.functions_panel.contains(mouse) ||
+		self.state_panel.contains(mouse) ||
+		self.triggers_panel.contains(mouse)
+	}
+
+	/// Position of the mouse in world coordinates.
+	fn mouse_world_position(&self) -> Vector2 {
+		self.rl_handle.get_screen_to_world2D(self.rl_handle.get_mouse_position(), self.camera)
+	}
+
+	fn screen_center(&self) -> Vector2 {
+		Vector2::new(
+			self.rl_handle.get_screen_width() as f32 / 2f32,
+			self.rl_handle.get_screen_height() as f32 / 2f32
+		)
+	}
 
-		let force_menu_activations = self.force_menu.root.get_all_activations();
+	/// Centers the camera on the points and zooms so that they are all visible.
+	fn fit_all_points(&mut self) {
+		const MARGIN: f32 = 100f32;
 
-		if force_menu_activations.contains(&"apply".to_string()) {
-			match (
-				Tokenizer::tokenize(&self.force_menu.root.get_entry_in_tree("set ax").unwrap_or("0".to_string())),
-				Tokenizer::tokenize(&self.force_menu.root.get_entry_in_tree("set ay").unwrap_or("0".to_string()))
-			) {
-				(Ok(tx), Ok(ty)) => {
-					self.forces.borrow_mut().insert(self.selected_force.clone(), Force {x:tx, y:ty});
-					self.selected_force.clear();
-					self.force_menu.root.set_visible(false);
+		if self.world.is_empty() {
+			return;
+		}
+
+		let mut min = self.world[0].position();
+		let mut max = self.world[0].position();
+		for p in self.world.iter() {
+			min.x = min.x.min(p.position().x);
+			min.y = min.y.min(p.position().y);
+			max.x = max.x.max(p.position().x);
+			max.y = max.y.max(p.position().y);
+		}
+
+		let size = max - min + Vector2::new(MARGIN, MARGIN);
+		self.camera.offset = self.screen_center();
+		self.camera.target = (min + max) / 2f32;
+		self.camera.zoom = (2f32 * self.camera.offset.x / size.x)
+			.min(2f32 * self.camera.offset.y / size.y)
+			.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+	}
+
+	/// Mouse wheel zoom, middle click pan and selected point following.
+	fn camera_events(&mut self) {
+		let mouse = self.rl_handle.get_mouse_position();
+
+		let wheel = self.rl_handle.get_mouse_wheel_move();
+		if wheel != 0f32 && !self.is_mouse_over_ui() {
+			// Zooming around the mouse: the world point under it must stay under it
+			self.camera.target = self.mouse_world_position();
+			self.camera.offset = mouse;
+			self.camera.zoom = (self.camera.zoom * (1f32 + 0.1f32 * wheel)).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+		}
+
+		if self.rl_handle.is_mouse_button_down(MouseButton::MOUSE_BUTTON_MIDDLE) {
+			self.follow_selected = false;
+			self.camera.target -= self.rl_handle.get_mouse_delta() / self.camera.zoom;
+		}
+
+		if self.follow_selected {
+			if let Some(i) = self.selected_point {
+				self.camera.offset = self.screen_center();
+				self.camera.target = self.world[i].position();
+			}
+		}
+
+		if let Some(w) = self.camera_controls.seek("follow label") {
+			if let WidgetVariant::Label {text, ..} = w.get_variant() {
+				*text = String::from(if self.follow_selected {"Unfollow"} else {"Follow"});
+			}
+		}
+	}
+
+	fn is_typing(&self) -> bool {
+		self.force_menu.is_typing() ||
+		self.force_naming.is_typing() ||
+		self.point_menu.is_typing() ||
+		self.trail_menu.is_typing() ||
+		self.plot_menu.is_typing() ||
+		self.parameters_panel.is_typing() ||
+		self.functions_panel.is_typing() ||
+		self.state_panel.is_typing() ||
+		self.triggers_panel.is_typing()
+	}
+
+	/// Shows which arrows are enabled in the arrow controls labels.
+	fn update_arrow_controls(&mut self) {
+		let texts = [
+			("speed arrows label", format!("v: {}", if self.arrows.speed {"on"} else {"off"})),
+			("acceleration arrows label", format!("a: {}", if self.arrows.acceleration {"on"} else {"off"})),
+			("force arrows label", format!("F: {}", if self.arrows.forces {"on"} else {"off"})),
+			("arrows scope label", String::from(if self.arrows_on_all_points {"All"} else {"Sel."}))
+		];
+
+		for (id, t) in texts {
+			if let Some(w) = self.arrow_controls.seek(id) {
+				if let WidgetVariant::Label {text, ..} = w.get_variant() {
+					*text = t;
+				}
+			}
+		}
+	}
+
+	/// Reads the trail width slider and shows the other trail options in the trail menu labels.
+	fn update_trail_menu(&mut self) {
+		self.trail_options.thickness = self.trail_menu.root.get_value_in_tree("trail thickness").unwrap_or(10f32);
+
+		let texts = [
+			("trail coloring label", String::from(match self.trail_options.coloring {
+				TrailColoring::Time => "Color: time",
+				TrailColoring::Speed => "Color: speed",
+				TrailColoring::Point => "Color: point"
+			})),
+			("trail fade label", format!("Fade: {}", if self.trail_options.fade {"on"} else {"off"}))
+		];
+
+		for (id, t) in texts {
+			if let Some(w) = self.trail_menu.seek(id) {
+				if let WidgetVariant::Label {text, ..} = w.get_variant() {
+					*text = t;
 				}
-				(Err(e), _) => println!("Error on X expression : {e:?}"),
-				(_, Err(e)) => println!("Error on Y expression : {e:?}")
 			}
-					
 		}
+	}
+
+	/// Refreshes the pause button and the status label of the simulation controls.
+	fn update_sim_controls(&mut self) {
+		let speed = self.sim_controls.root.get_value_in_tree("speed").unwrap_or(1f32);
+		let status = format!(
+			"{}t={} x{:.2}",
+			if self.paused {"PAUSED "} else {""},
+			self.environment.borrow().time,
+			speed
+		);
+
+		if let Some(w) = self.sim_controls.seek("sim status") {
+			if let WidgetVariant::Label {text, ..} = w.get_variant() {
+				*text = status;
+			}
+		}
+		if let Some(w) = self.sim_controls.seek("pause label") {
+			if let WidgetVariant::Label {text, ..} = w.get_variant() {
+				*text = String::from(if self.paused {"Play"} else {"Pause"});
+			}
+		}
+	}
+
+	/// Mouse interactions with the points: click to select, drag to move and release to throw.
+	fn world_events(&mut self) {
+		let mouse = self.mouse_world_position();
+		let zoom = self.camera.zoom;
+
+		if self.rl_handle.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && !self.is_mouse_over_ui() {
+			// Picking the closest point under the mouse
+			let picked = self.world.iter()
+				.enumerate()
+				.filter(|(_, p)| p.is_under(mouse, zoom))
+				.min_by(|(_, a), (_, b)| a.position().distance_to(mouse).total_cmp(&b.position().distance_to(mouse)))
+				.map(|(i, _)| i);
+
+			if let Some(i) = picked {
+				self.dispatch(Message::SelectPoint(i));
+				self.dragged_point = Some(i);
+				self.drag_offset = self.world[i].position() - mouse;
+				self.drag_speed = Vector2::zero();
+			}
+		}
+
+		if let Some(i) = self.dragged_point {
+			if self.rl_handle.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+				// Smoothing mouse motion so that the release speed doesn't only depend on the last frame
+				let target = mouse + self.drag_offset;
+				self.drag_speed = self.drag_speed * 0.5f32 + (target - self.world[i].position()) * 0.5f32;
+				self.world[i].drag_to(target);
+			} else {
+				self.world[i].set_speed(self.drag_speed);
+				self.dragged_point = None;
+				self.reset_drift();
+			}
+		}
+	}
+
+	fn contextual_menu_events(&mut self) {
+		if self.rl_handle.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+			self.contextual_menu.bounds.center = self.rl_handle.get_mouse_position() + self.contextual_menu.bounds.size/2f32;
+			self.contextual_menu.root.set_visible(true);
+		}
+		if self.rl_handle.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && !self.contextual_menu.bounds.contains(self.rl_handle.get_mouse_position()) {
+			self.contextual_menu.root.set_visible(false);
+		}
+	}
 
+	fn force_menu_events(&mut self) {
 		if 
 			!self.force_menu.bounds.contains(self.rl_handle.get_mouse_position()) &&
 			self.rl_handle.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) &&
@@ -490,40 +2629,7 @@ impl Application {
 		}
 	}
 
-	fn force_naming_events(&mut self) {
-		let activations = self.force_naming.root.get_all_activations();
-
-		for id in activations {
-			if id == "cancel" {
-				self.force_naming.root.set_visible(false);
-			} else if id == "create" {
-				match self.force_inspector.seek("force scroll") {
-					Some (s) => {
-						let name = match self.force_naming.seek("name") {
-							Some(n) => {
-								if let WidgetVariant::TextInput{text, cursor, ..} = n.get_variant() {
-									let t = text.clone();
-									text.clear();
-									*cursor = 0u32;
-									t
-								} else {
-									println!("Error: Widget of ID 'name' is not a TextInput.");
-									String::from("Unknown")
-								}
-							},
-							None => String::from("Unknown")
-						};
-						self.forces.borrow_mut().insert(name.clone(), Force::new());
-						Self::add_button_to_scroll(s, |_: u32| name.clone());
-						self.force_naming.root.set_visible(false);
-					},
-					None => println!("Error: No scroll menu in force inspector, what happened ?")
-				}
-			}
-		}
-	}
-
-	fn add_button_to_scroll(s: &mut Widget, t: impl Fn(u32) -> String) {
+	fn add_button_to_scroll(s: &mut Widget<Message>, text: String, message: Message) {
 		let children_count = s.get_children_count(1u32);
 		let h =  children_count as f32 * 0.1f32 - 0.4f32;
 		s.add_child_inplace(
@@ -534,14 +2640,14 @@ impl Application {
 				),
 				WidgetVariant::Button {state: ButtonState::Rest}
 			)
-			.id(t(children_count))
+			.on_activation(message)
 			.add_child(
 				Widget::new(
 					Layout::new(
 						Vector2::new(0f32, 0f32),
 						Vector2::new(1f32, 1f32)
 					),
-					WidgetVariant::Label {text: t(children_count), font_size: 16i32}
+					WidgetVariant::Label {text, font_size: 16i32}
 				).style(Style::default().background(Color::new(0, 0, 0, 0)))
 			)	
 		)
@@ -0,0 +1,80 @@
+//! Affiche le champ qu'une force produirait sur une sonde immobile, pour vérifier une expression
+//! avant d'y lâcher des points.
+
+use raylib::prelude::*;
+use super::super::model::physics::*;
+use super::super::model::random::{RandomStream, WORLD_STREAM};
+
+/// Arrows showing a force sampled over the visible area, for a unit mass probe at rest.
+pub struct ForceField {
+	name: String,
+	force: Force
+}
+
+impl ForceField {
+	/// On-screen distance between two samples, in pixels.
+	const SPACING: f32 = 40f32;
+
+	pub fn new(name: String, force: Force) -> Self {
+		ForceField {
+			name,
+			force
+		}
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn force(&self) -> &Force {
+		&self.force
+	}
+
+	/// Draws the field in screen coordinates. Arrows are scaled relatively to the strongest
+	/// sample so that the field stays readable whatever the force magnitude. Random forces show
+	/// one draw per sample.
+	pub fn draw(&self, camera: &Camera2D, environment: &Environment, handle: &mut RaylibDrawHandle) {
+		let screen = Vector2::new(handle.get_screen_width() as f32, handle.get_screen_height() as f32);
+
+		let mut samples = Vec::<(Vector2, Vector2)>::new(); // (screen position, force)
+		let mut random = RandomStream::new(environment.seed, WORLD_STREAM, environment.time as u64);
+		let mut y = Self::SPACING / 2f32;
+		while y < screen.y {
+			let mut x = Self::SPACING / 2f32;
+			while x < screen.x {
+				let world = (Vector2::new(x, y) - camera.offset) / camera.zoom + camera.target;
+				match self.force.evaluate(&Point::probe_context(world, environment), &mut random) {
+					Ok(f) => samples.push((Vector2::new(x, y), f)),
+					Err(e) => {
+						handle.draw_text(&format!("Field of {} : {e}", self.name), 210, 40, 16, Color::RED);
+						return;
+					}
+				}
+				x += Self::SPACING;
+			}
+			y += Self::SPACING;
+		}
+
+		let max = samples.iter()
+			.map(|(_, f)| f.length())
+			.filter(|l| l.is_finite())
+			.fold(0f32, f32::max);
+		if max == 0f32 {
+			return;
+		}
+
+		for (position, force) in samples {
+			let prop = force.length() / max;
+			if !prop.is_finite() {
+				continue;
+			}
+
+			// Blue for weak samples, red for the strongest ones
+			let color = Color::new((255f32 * prop) as u8, 0, (255f32 * (1f32 - prop)) as u8, 150);
+			let half = force / max * Self::SPACING * 0.4f32;
+			draw_arrow(position - half, position + half, 1f32, color, handle);
+		}
+
+		handle.draw_text(&format!("Field of {}", self.name), 210, 40, 16, Color::DARKGRAY);
+	}
+}
@@ -0,0 +1,110 @@
+//! Dessine le repère du monde (grille, axes, origine et échelle) ainsi que la position du curseur.
+
+use raylib::prelude::*;
+
+/// Background grid whose spacing adapts to the zoom level of the camera.
+pub struct Grid {
+	pub visible: bool
+}
+
+impl Grid {
+	/// Minimum on-screen distance between two grid lines, in pixels.
+	const MIN_SPACING: f32 = 50f32;
+	const FONT_SIZE: i32 = 10;
+
+	pub fn new() -> Self {
+		Grid {
+			visible: true
+		}
+	}
+
+	pub fn toggle(&mut self) {
+		self.visible = !self.visible;
+	}
+
+	/// Returns the smallest round world length (1, 2 or 5 times a power of ten)
+	/// which is at least MIN_SPACING pixels long on screen.
+	pub fn spacing(zoom: f32) -> f32 {
+		let min_length = Self::MIN_SPACING / zoom;
+		let magnitude = 10f32.powf(min_length.log10().floor());
+
+		for m in [1f32, 2f32, 5f32] {
+			if m * magnitude >= min_length {
+				return m * magnitude;
+			}
+		}
+		10f32 * magnitude
+	}
+
+	/// Formats a graduation with just enough decimals for the given spacing.
+	fn format_graduation(value: f32, spacing: f32) -> String {
+		let decimals = (-spacing.log10().floor()).max(0f32) as usize;
+		format!("{:.*}", decimals, value)
+	}
+
+	/// Draws the grid, the axes, the origin and the scale ruler in screen coordinates.
+	/// Call it before drawing the points so that it stays in the background.
+	pub fn draw(&self, camera: &Camera2D, handle: &mut RaylibDrawHandle) {
+		if !self.visible {
+			return;
+		}
+
+		let screen = Vector2::new(handle.get_screen_width() as f32, handle.get_screen_height() as f32);
+		let to_screen = |world: Vector2| (world - camera.target) * camera.zoom + camera.offset;
+		let top_left = (Vector2::zero() - camera.offset) / camera.zoom + camera.target;
+		let bottom_right = (screen - camera.offset) / camera.zoom + camera.target;
+
+		let spacing = Self::spacing(camera.zoom);
+		let origin = to_screen(Vector2::zero());
+
+		// Graduations are written along the axes, or along the screen border if an axis is out of view.
+		let label_x = origin.x.clamp(2f32, screen.x - 40f32);
+		let label_y = origin.y.clamp(2f32, screen.y - Self::FONT_SIZE as f32 - 2f32);
+
+		// Vertical lines
+		let mut k = (top_left.x / spacing).ceil() as i64;
+		while k as f32 * spacing <= bottom_right.x {
+			let x = to_screen(Vector2::new(k as f32 * spacing, 0f32)).x;
+			if k == 0 {
+				handle.draw_line_ex(Vector2::new(x, 0f32), Vector2::new(x, screen.y), 2f32, Color::DARKGRAY);
+			} else {
+				handle.draw_line_v(Vector2::new(x, 0f32), Vector2::new(x, screen.y), Color::new(0, 0, 0, 30));
+				handle.draw_text(&Self::format_graduation(k as f32 * spacing, spacing), x as i32 + 2, label_y as i32, Self::FONT_SIZE, Color::GRAY);
+			}
+			k += 1;
+		}
+
+		// Horizontal lines
+		let mut k = (top_left.y / spacing).ceil() as i64;
+		while k as f32 * spacing <= bottom_right.y {
+			let y = to_screen(Vector2::new(0f32, k as f32 * spacing)).y;
+			if k == 0 {
+				handle.draw_line_ex(Vector2::new(0f32, y), Vector2::new(screen.x, y), 2f32, Color::DARKGRAY);
+			} else {
+				handle.draw_line_v(Vector2::new(0f32, y), Vector2::new(screen.x, y), Color::new(0, 0, 0, 30));
+				handle.draw_text(&Self::format_graduation(k as f32 * spacing, spacing), label_x as i32 + 2, y as i32 + 2, Self::FONT_SIZE, Color::GRAY);
+			}
+			k += 1;
+		}
+
+		// Origin
+		handle.draw_circle_lines(origin.x as i32, origin.y as i32, 4f32, Color::DARKGRAY);
+		handle.draw_text("0", origin.x as i32 + 3, origin.y as i32 + 3, Self::FONT_SIZE, Color::DARKGRAY);
+		handle.draw_text("x", screen.x as i32 - 12, origin.y as i32 - 12, Self::FONT_SIZE, Color::DARKGRAY);
+		handle.draw_text("y", origin.x as i32 + 4, screen.y as i32 - 12, Self::FONT_SIZE, Color::DARKGRAY);
+
+		// Scale ruler, in the top left corner
+		let ruler_start = Vector2::new(10f32, 12f32);
+		let ruler_end = ruler_start + Vector2::new(spacing * camera.zoom, 0f32);
+		handle.draw_line_ex(ruler_start, ruler_end, 2f32, Color::BLACK);
+		handle.draw_line_v(ruler_start - Vector2::new(0f32, 4f32), ruler_start + Vector2::new(0f32, 4f32), Color::BLACK);
+		handle.draw_line_v(ruler_end - Vector2::new(0f32, 4f32), ruler_end + Vector2::new(0f32, 4f32), Color::BLACK);
+		handle.draw_text(&Self::format_graduation(spacing, spacing), ruler_end.x as i32 + 5, ruler_start.y as i32 - Self::FONT_SIZE / 2, Self::FONT_SIZE, Color::BLACK);
+	}
+}
+
+/// Writes the world coordinates of the mouse in the bottom left corner of the screen.
+pub fn draw_cursor_readout(mouse_world: Vector2, handle: &mut RaylibDrawHandle) {
+	let y = handle.get_screen_height() - 18;
+	handle.draw_text(&format!("px = {:.1}  py = {:.1}", mouse_world.x, mouse_world.y), 5, y, 14, Color::BLACK);
+}
@@ -0,0 +1,78 @@
+//! Keeps persistent trails in a texture, so that each frame only draws the segments
+//! recorded since the previous one however long the trails get.
+
+use raylib::prelude::*;
+use crate::model::physics::{World, TrailOptions};
+
+pub struct TraceLayer {
+	texture: RenderTexture2D,
+	// Camera and options the texture content was drawn with
+	camera: Camera2D,
+	options: TrailOptions,
+	outdated: bool
+}
+
+impl TraceLayer {
+	pub fn new(handle: &mut RaylibHandle, thread: &RaylibThread, options: TrailOptions) -> Result<Self, String> {
+		let width = handle.get_screen_width() as u32;
+		let height = handle.get_screen_height() as u32;
+
+		Ok(TraceLayer {
+			texture: handle.load_render_texture(thread, width, height)?,
+			camera: Camera2D {offset: Vector2::zero(), target: Vector2::zero(), rotation: 0f32, zoom: 1f32},
+			options,
+			outdated: true
+		})
+	}
+
+	/// Redraws every persistent trail on the next `draw`, e.g. after a point was removed.
+	pub fn invalidate(&mut self) {
+		self.outdated = true;
+	}
+
+	/// Draws the new persistent trail segments of `world` into the layer, then the layer onto the screen.
+	pub fn draw(&mut self, world: &mut World, camera: &Camera2D, options: TrailOptions, thread: &RaylibThread, handle: &mut RaylibDrawHandle) {
+		let camera_moved = self.camera.offset != camera.offset
+			|| self.camera.target != camera.target
+			|| self.camera.rotation != camera.rotation
+			|| self.camera.zoom != camera.zoom;
+
+		if camera_moved || options != self.options {
+			self.outdated = true;
+			self.camera = *camera;
+			self.options = options;
+		}
+
+		{
+			let mut texture_handle = handle.begin_texture_mode(thread, &mut self.texture);
+			let mut world_handle = texture_handle.begin_mode2D(*camera);
+
+			// A point may report its trace as outdated while drawing, in which case everything
+			// is drawn again from scratch
+			for _ in 0..2 {
+				if self.outdated {
+					world_handle.clear_background(Color::BLANK);
+					for point in world.iter_mut() {
+						point.reset_trace();
+					}
+					self.outdated = false;
+				}
+
+				for point in world.iter_mut() {
+					if !point.draw_new_trace_segments(options, camera.zoom, &mut world_handle) {
+						self.outdated = true;
+					}
+				}
+
+				if !self.outdated {
+					break;
+				}
+			}
+		}
+
+		// Render textures are stored upside down
+		let width = self.texture.texture().width as f32;
+		let height = self.texture.texture().height as f32;
+		handle.draw_texture_rec(&self.texture, Rectangle::new(0f32, 0f32, width, -height), Vector2::zero(), Color::WHITE);
+	}
+}
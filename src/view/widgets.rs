@@ -2,13 +2,14 @@
 
 
 use raylib::prelude::*;
+use std::collections::VecDeque;
 
-pub struct WidgetTree {
-	pub root: Widget,
+pub struct WidgetTree<M> {
+	pub root: Widget<M>,
 	pub bounds: Layout
 }
-impl WidgetTree {
-	pub fn new(root: Widget, bounds: Layout) -> Self {
+impl<M: Clone> WidgetTree<M> {
+	pub fn new(root: Widget<M>, bounds: Layout) -> Self {
 		Self {
 			root,
 			bounds
@@ -23,13 +24,37 @@ impl WidgetTree {
 		self.root.draw_tree(&self.bounds, rl);
 	}
 
-	pub fn seek(&mut self, id: &'static str) -> Option<&mut Widget> {
+	pub fn seek(&mut self, id: &str) -> Option<&mut Widget<M>> {
 		self.root.seek_in_tree(id)
 	}
 
+	/// Returns the messages of every Button activated since last call.
+	pub fn poll_messages(&mut self) -> Vec::<M> {
+		self.root.get_all_activations()
+	}
+
 	pub fn get_entry(&self, id: &'static str) -> Option<String> {
 		self.root.get_entry_in_tree(id)
 	}
+
+	/// Text of a TextInput as typed, unlike `get_entry` which reads empty inputs as "0".
+	pub fn get_text(&self, id: &'static str) -> Option<String> {
+		WidgetTreeIterator::new(&self.root).find_map(|w| match &w.variant {
+			WidgetVariant::TextInput {text, ..} if w.id == id => Some(text.clone()),
+			_ => None
+		})
+	}
+
+	/// Whether the tree is visible and covers `position`.
+	pub fn contains(&self, position: Vector2) -> bool {
+		!self.root.is_hidden() && self.bounds.contains(position)
+	}
+
+	/// Whether the user is currently typing in one of the TextInputs of the tree.
+	/// Useful to ignore keyboard shortcuts while typing.
+	pub fn is_typing(&self) -> bool {
+		WidgetTreeIterator::new(&self.root).any(|w| matches!(w.variant, WidgetVariant::TextInput {selected: true, ..}))
+	}
 } 
 
 
@@ -106,11 +131,32 @@ pub enum WidgetVariant {
 	Label {text: String, font_size: i32},
 	Button {state: ButtonState},
 	TextInput {selected: bool, placeholder: String, text: String, cursor: u32, registered: bool},
-	Scroll {offset: f32}
+	Scroll {offset: f32},
+	Slider {value: f32, min: f32, max: f32, grabbed: bool},
+	/// Curves drawn with automatically scaled axes. Each series keeps its last `window` samples.
+	Plot {series: Vec::<PlotSeries>, window: usize}
+}
+
+/// A curve of a `WidgetVariant::Plot`, as (x, y) samples in insertion order.
+#[derive(Debug)]
+pub struct PlotSeries {
+	pub label: String,
+	pub color: Color,
+	pub samples: VecDeque::<Vector2>
+}
+impl PlotSeries {
+	pub fn new(label: String, color: Color) -> Self {
+		PlotSeries {
+			label,
+			color,
+			samples: VecDeque::<Vector2>::new()
+		}
+	}
 }
 
 
 /// Représente un élément de l'interface graphique utilisateur (bouton, text, champ d'entrée, etc.)
+/// Les boutons émettent un message de type `M` lorsqu'ils sont activés (voir `Widget::on_activation`).
 ///
 /// # Exemple
 /// ```
@@ -118,7 +164,7 @@ pub enum WidgetVariant {
 /// use view::widgets::*;
 ///
 /// fn main() {
-///     let mut w = Widget::new(
+///     let mut w = Widget::<()>::new(
 ///         Layout::new(
 ///             Vector2::new(0f32, 0f32),
 ///             Vector2::new(0.9f32, 0.9f32)
@@ -151,27 +197,29 @@ pub enum WidgetVariant {
 ///     }
 /// }
 /// ```
-pub struct Widget {
+pub struct Widget<M> {
 	layout: Layout,
 	variant: WidgetVariant,
 	style: Style,
-	children: Vec::<Widget>,
+	children: Vec::<Widget<M>>,
 	hidden: bool,
-	id: String
+	id: String,
+	message: Option<M>
 }
 
-impl Widget {
+impl<M: Clone> Widget<M> {
 
 	// __________________________________Constructor______________________________________
 
-	pub fn new(layout: Layout, variant: WidgetVariant) -> Widget{
+	pub fn new(layout: Layout, variant: WidgetVariant) -> Widget<M> {
 		Widget {
 			layout,
 			variant,
 			style: Style::default(),
-			children: Vec::<Widget>::new(),
+			children: Vec::<Widget<M>>::new(),
 			hidden: false,
-			id: String::from("Unknown")
+			id: String::from("Unknown"),
+			message: None
 		}
 	}
 	pub fn style(mut self, style: Style) -> Self {
@@ -192,6 +240,12 @@ impl Widget {
 		self
 	}
 
+	/// Sets the message emitted when this widget (a Button) is activated.
+	pub fn on_activation(mut self, message: M) -> Self {
+		self.message = Some(message);
+		self
+	}
+
 	// ___________________________________Setters__________________________________
 
 	pub fn set_visible(&mut self, a: bool) {
@@ -214,7 +268,7 @@ impl Widget {
 
 	// __________________________________Tree tools______________________________________
 
-	pub fn seek_in_tree(&mut self, id: &'static str) -> Option<&mut Widget> {
+	pub fn seek_in_tree(&mut self, id: &str) -> Option<&mut Widget<M>> {
 		if self.id == id {
 			return Some(self);
 		}
@@ -251,12 +305,22 @@ impl Widget {
 		return None;
 	}
 
+	/// Returns the value of the first Slider of a given id encountered in depth-first iteration.
+	pub fn get_value_in_tree(&self, id: &str) -> Option<f32> {
+		WidgetTreeIterator::new(self)
+			.filter(|w| w.id == id)
+			.find_map(|w| match w.variant {
+				WidgetVariant::Slider {value, ..} => Some(value),
+				_ => None
+			})
+	}
+
 	/// Returns the content of the first unregistered TextInput of a given id encountered
 	/// in depth-first iteration and flag it as registered.
 	/// # When to use ?
 	/// Useful if you call this method multiple times and want it to truly execute only
 	/// once per user validation.
-	pub fn check_entry_in_tree(&mut self, id: &'static str) -> Option<String> {
+	pub fn check_entry_in_tree(&mut self, id: &str) -> Option<String> {
 		if self.id == id {
 			if let WidgetVariant::TextInput {text, registered, ..} = &mut self.variant {
 				if !*registered {
@@ -279,18 +343,22 @@ impl Widget {
 		return None;
 	}
 
-	pub fn get_all_activations(&mut self) -> Vec::<String> {
-		let mut r = Vec::<String>::new();
+	/// Returns the messages of every activated but not yet handled Button of the tree
+	/// and flags them as handled. Buttons without message are flagged but ignored.
+	pub fn get_all_activations(&mut self) -> Vec::<M> {
+		let mut r = Vec::<M>::new();
 		
 		if let WidgetVariant::Button{state: ButtonState::Activated{handled, ..}} = &mut self.variant {
 			if !*handled {
 				*handled = true;
-				r.push(self.id.clone());
+				if let Some(m) = &self.message {
+					r.push(m.clone());
+				}
 			} 
 		}
 
 		for c in self.children.iter_mut() {
-			c.get_all_activations().iter().for_each(|x| r.push(x.clone()));
+			r.append(&mut c.get_all_activations());
 		}
 
 		r
@@ -343,6 +411,9 @@ impl Widget {
 			WidgetVariant::Scroll {offset} => {
 				Self::handle_events_as_scroll(offset, &true_coords, mouse, rl);
 				true_coords.center.y += *offset;
+			},
+			WidgetVariant::Slider {value, min, max, grabbed} => {
+				Self::handle_events_as_slider(value, *min, *max, grabbed, &true_coords, mouse, rl);
 			}
 			_ => {}
 		}
@@ -386,6 +457,12 @@ impl Widget {
 				// Draw here, not after next line.
 				// (or else scrollbar will be offseted too)
 				true_coords.center.y += offset;
+			},
+			WidgetVariant::Slider {value, min, max, grabbed} => {
+				self.draw_as_slider(*value, *min, *max, *grabbed, coords_rect, draw_handle);
+			},
+			WidgetVariant::Plot {series, ..} => {
+				self.draw_as_plot(series, coords_rect, draw_handle);
 			}
 		}
 
@@ -396,15 +473,32 @@ impl Widget {
 		}
 	}
 
-	pub fn add_child(mut self, w: Widget) -> Self {
+	/// Adds a sample to the series of index `series` of a Plot, forgetting samples that
+	/// fall out of its window. Does nothing on other variants.
+	pub fn push_plot_sample(&mut self, series: usize, sample: Vector2) {
+		if let WidgetVariant::Plot {series: s, window} = &mut self.variant {
+			if let Some(s) = s.get_mut(series) {
+				s.samples.push_back(sample);
+				while s.samples.len() > (*window).max(1) {
+					s.samples.pop_front();
+				}
+			}
+		}
+	}
+
+	pub fn add_child(mut self, w: Widget<M>) -> Self {
 		self.children.push(w);
 		self
 	}
 
-	pub fn add_child_inplace(&mut self, w: Widget) {
+	pub fn add_child_inplace(&mut self, w: Widget<M>) {
 		self.children.push(w);
 	}
 
+	pub fn clear_children(&mut self) {
+		self.children.clear();
+	}
+
 	// Returns the number of children closer than depth.
 	pub fn get_children_count(&self, depth: u32) -> u32{
 		if depth == 0u32 {
@@ -496,8 +590,116 @@ impl Widget {
 		);
 	}
 
+	fn draw_as_slider(&self, value: f32, min: f32, max: f32, grabbed: bool, coords_rect: Rectangle, draw_handle: &mut RaylibDrawHandle) {
+		let prop = if max > min {((value - min) / (max - min)).clamp(0f32, 1f32)} else {0f32};
+		let middle = coords_rect.y + coords_rect.height / 2f32;
+
+		draw_handle.draw_rectangle_rec(coords_rect, self.style.background);
+		draw_handle.draw_line_ex(
+			Vector2::new(coords_rect.x, middle),
+			Vector2::new(coords_rect.x + coords_rect.width, middle),
+			2f32,
+			self.style.foreground
+		);
+		draw_handle.draw_rectangle_rec(
+			Rectangle::new(coords_rect.x + prop * coords_rect.width - 3f32, coords_rect.y, 6f32, coords_rect.height),
+			if grabbed {self.style.action} else {self.style.foreground}
+		);
+	}
+
+	fn draw_as_plot(&self, series: &[PlotSeries], coords_rect: Rectangle, draw_handle: &mut RaylibDrawHandle) {
+		const FONT_SIZE: i32 = 10i32;
+
+		draw_handle.draw_rectangle_rec(coords_rect, self.style.background);
+		draw_handle.draw_rectangle_lines_ex(coords_rect, 1f32, self.style.foreground);
+
+		// Axes bounds fitting every sample
+		let mut min = Vector2::new(f32::INFINITY, f32::INFINITY);
+		let mut max = Vector2::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
+		for sample in series.iter().flat_map(|s| s.samples.iter()) {
+			min.x = min.x.min(sample.x);
+			min.y = min.y.min(sample.y);
+			max.x = max.x.max(sample.x);
+			max.y = max.y.max(sample.y);
+		}
+		if min.x > max.x {
+			draw_handle.draw_text("No data", coords_rect.x as i32 + 5i32, coords_rect.y as i32 + 5i32, FONT_SIZE, self.style.foreground);
+			return;
+		}
+		// Flat curves still need a range to be drawn
+		if max.x <= min.x {
+			min.x -= 1f32;
+			max.x += 1f32;
+		}
+		if max.y <= min.y {
+			min.y -= 1f32;
+			max.y += 1f32;
+		}
+
+		let to_screen = |sample: Vector2| Vector2::new(
+			coords_rect.x + (sample.x - min.x) / (max.x - min.x) * coords_rect.width,
+			coords_rect.y + (max.y - sample.y) / (max.y - min.y) * coords_rect.height
+		);
+
+		// Zero lines when in view
+		if min.x < 0f32 && max.x > 0f32 {
+			let x = to_screen(Vector2::zero()).x;
+			draw_handle.draw_line_ex(Vector2::new(x, coords_rect.y), Vector2::new(x, coords_rect.y + coords_rect.height), 1f32, self.style.action);
+		}
+		if min.y < 0f32 && max.y > 0f32 {
+			let y = to_screen(Vector2::zero()).y;
+			draw_handle.draw_line_ex(Vector2::new(coords_rect.x, y), Vector2::new(coords_rect.x + coords_rect.width, y), 1f32, self.style.action);
+		}
+
+		for (i, s) in series.iter().enumerate() {
+			for (a, b) in s.samples.iter().zip(s.samples.iter().skip(1)) {
+				draw_handle.draw_line_ex(to_screen(*a), to_screen(*b), 1.5f32, s.color);
+			}
+
+			// Legend in the top right corner
+			let width = draw_handle.measure_text(&s.label, FONT_SIZE);
+			draw_handle.draw_text(
+				&s.label,
+				(coords_rect.x + coords_rect.width) as i32 - width - 5i32,
+				coords_rect.y as i32 + 3i32 + i as i32 * (FONT_SIZE + 2i32),
+				FONT_SIZE,
+				s.color
+			);
+		}
+
+		// Bounds of the axes
+		let left = coords_rect.x as i32 + 3i32;
+		let bottom = (coords_rect.y + coords_rect.height) as i32 - FONT_SIZE - 2i32;
+		let max_x = format!("{:.4}", max.x);
+		draw_handle.draw_text(&format!("{:.4}", max.y), left, coords_rect.y as i32 + 3i32, FONT_SIZE, self.style.foreground);
+		draw_handle.draw_text(&format!("{:.4}", min.y), left, bottom - FONT_SIZE - 2i32, FONT_SIZE, self.style.foreground);
+		draw_handle.draw_text(&format!("{:.4}", min.x), left, bottom, FONT_SIZE, self.style.foreground);
+		draw_handle.draw_text(
+			&max_x,
+			(coords_rect.x + coords_rect.width) as i32 - draw_handle.measure_text(&max_x, FONT_SIZE) - 5i32,
+			bottom,
+			FONT_SIZE,
+			self.style.foreground
+		);
+	}
+
 	// Events
 
+	fn handle_events_as_slider(value: &mut f32, min: f32, max: f32, grabbed: &mut bool, true_coords: &Layout, mouse: Vector2, rl: &mut RaylibHandle) {
+		if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && true_coords.contains(mouse) {
+			*grabbed = true;
+		}
+		if !rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+			*grabbed = false;
+		}
+
+		if *grabbed {
+			let left = true_coords.center.x - true_coords.size.x / 2f32;
+			let prop = ((mouse.x - left) / true_coords.size.x).clamp(0f32, 1f32);
+			*value = min + prop * (max - min);
+		}
+	}
+
 	fn handle_events_as_scroll(offset: &mut f32, true_coords: &Layout, mouse: Vector2, rl: &mut RaylibHandle) {
 
 		if true_coords.contains(mouse) {
@@ -591,7 +793,7 @@ impl Widget {
 /// L'ordre utilisé est celui du parcours par profondeur.
 /// # Exemple
 /// ```
-/// let w = Widget::new(
+/// let w = Widget::<()>::new(
 /// Layout::new(
 ///        Vector2::new(0f32, 0f32),
 ///        Vector2::new(1f32, 1f32)
@@ -622,20 +824,20 @@ impl Widget {
 ///     println!("Ce widget est caché : {}", widget.is_hidden());
 /// }
 /// ```
-pub struct WidgetTreeIterator<'a> {
-	stack: Vec::<&'a Widget>
+pub struct WidgetTreeIterator<'a, M> {
+	stack: Vec::<&'a Widget<M>>
 }
 
-impl <'a>WidgetTreeIterator<'a> {
-	pub fn new(w: &'a Widget) -> Self {
+impl <'a, M>WidgetTreeIterator<'a, M> {
+	pub fn new(w: &'a Widget<M>) -> Self {
 		WidgetTreeIterator {
 			stack: vec![w]
 		}
 	}
 }
 
-impl <'a> Iterator for WidgetTreeIterator<'a> {
-	type Item = &'a Widget;
+impl <'a, M> Iterator for WidgetTreeIterator<'a, M> {
+	type Item = &'a Widget<M>;
 
 	fn next(&mut self) -> Option<Self::Item> {
 		let p = self.stack.pop();